@@ -0,0 +1,1243 @@
+/// A template `init` can add to a project, with its base, schema and example overlays
+pub struct CatalogueTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub base: &'static str,
+    pub schema: &'static str,
+    /// Example overlays, by file name
+    pub overlays: &'static [(&'static str, &'static str)],
+}
+
+/// The templates built into overl
+pub const CATALOGUE: [CatalogueTemplate; 5] = [
+    CatalogueTemplate {
+        name: "argocd",
+        description: "ArgoCD Application",
+        base: ARGOCD_BASE_FILE,
+        schema: ARGOCD_JSONSCHEMA,
+        overlays: &[("helm_app.yaml", ARGOCD_OVERLAY_FILE)],
+    },
+    CatalogueTemplate {
+        name: "deployment",
+        description: "Kubernetes Deployment",
+        base: DEPLOYMENT_BASE_FILE,
+        schema: DEPLOYMENT_JSONSCHEMA,
+        overlays: &[("prod.yaml", DEPLOYMENT_OVERLAY_FILE)],
+    },
+    CatalogueTemplate {
+        name: "helm-values",
+        description: "Helm chart values",
+        base: HELM_VALUES_BASE_FILE,
+        schema: HELM_VALUES_JSONSCHEMA,
+        overlays: &[("prod.yaml", HELM_VALUES_OVERLAY_FILE)],
+    },
+    CatalogueTemplate {
+        name: "github-actions",
+        description: "GitHub Actions workflow",
+        base: GITHUB_ACTIONS_BASE_FILE,
+        schema: GITHUB_ACTIONS_JSONSCHEMA,
+        overlays: &[("release.yaml", GITHUB_ACTIONS_OVERLAY_FILE)],
+    },
+    CatalogueTemplate {
+        name: "compose",
+        description: "docker-compose service",
+        base: COMPOSE_BASE_FILE,
+        schema: COMPOSE_JSONSCHEMA,
+        overlays: &[("dev.yaml", COMPOSE_OVERLAY_FILE)],
+    },
+];
+
+/// Looks a template up in the catalogue by name
+pub fn find(name: &str) -> Option<&'static CatalogueTemplate> {
+    CATALOGUE.iter().find(|template| template.name == name)
+}
+
+const DEPLOYMENT_BASE_FILE: &str = r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+  labels:
+    app: app
+spec:
+  replicas: 1
+  selector:
+    matchLabels:
+      app: app
+  template:
+    metadata:
+      labels:
+        app: app
+    spec:
+      containers:
+        - name: app
+          image: nginx:1.27
+          ports:
+            - containerPort: 80
+"#;
+
+const DEPLOYMENT_OVERLAY_FILE: &str = r#"metadata:
+  namespace: production
+spec:
+  # More replicas to spread the production load
+  replicas: 3
+"#;
+
+const DEPLOYMENT_JSONSCHEMA: &str = r##""$schema": http://json-schema.org/draft-07/schema#
+title: Deployment
+type: object
+properties:
+  apiVersion:
+    const: apps/v1
+  kind:
+    const: Deployment
+  metadata:
+    "$ref": "#/definitions/Metadata"
+  spec:
+    type: object
+    properties:
+      replicas:
+        type: integer
+        minimum: 0
+        default: 1
+      selector:
+        type: object
+        properties:
+          matchLabels:
+            "$ref": "#/definitions/Labels"
+        required:
+        - matchLabels
+      strategy:
+        type: object
+        properties:
+          type:
+            enum:
+            - RollingUpdate
+            - Recreate
+      template:
+        type: object
+        properties:
+          metadata:
+            "$ref": "#/definitions/Metadata"
+          spec:
+            type: object
+            properties:
+              containers:
+                type: array
+                minItems: 1
+                items:
+                  "$ref": "#/definitions/Container"
+            required:
+            - containers
+        required:
+        - metadata
+        - spec
+    required:
+    - selector
+    - template
+required:
+- apiVersion
+- kind
+- metadata
+- spec
+definitions:
+  Labels:
+    type: object
+    additionalProperties:
+      type: string
+  Metadata:
+    type: object
+    properties:
+      name:
+        type: string
+      namespace:
+        type: string
+      labels:
+        "$ref": "#/definitions/Labels"
+      annotations:
+        "$ref": "#/definitions/Labels"
+  Container:
+    type: object
+    properties:
+      name:
+        type: string
+      image:
+        type: string
+      args:
+        type: array
+        items:
+          type: string
+      env:
+        type: array
+        items:
+          type: object
+          properties:
+            name:
+              type: string
+            value:
+              type: string
+          required:
+          - name
+      ports:
+        type: array
+        items:
+          type: object
+          properties:
+            name:
+              type: string
+            containerPort:
+              type: integer
+            protocol:
+              enum:
+              - TCP
+              - UDP
+              - SCTP
+          required:
+          - containerPort
+      resources:
+        type: object
+        properties:
+          limits:
+            "$ref": "#/definitions/Resources"
+          requests:
+            "$ref": "#/definitions/Resources"
+    required:
+    - name
+    - image
+  Resources:
+    type: object
+    properties:
+      cpu:
+        type:
+        - string
+        - number
+      memory:
+        type: string
+"##;
+
+const HELM_VALUES_BASE_FILE: &str = r#"replicaCount: 1
+image:
+  repository: nginx
+  tag: "1.27"
+  pullPolicy: IfNotPresent
+service:
+  type: ClusterIP
+  port: 80
+ingress:
+  enabled: false
+  hosts: []
+resources: {}
+"#;
+
+const HELM_VALUES_OVERLAY_FILE: &str = r#"replicaCount: 3
+ingress:
+  enabled: true
+  hosts:
+    - host: app.example.com
+      paths:
+        - path: /
+          pathType: Prefix
+resources:
+  limits:
+    cpu: 500m
+    memory: 256Mi
+"#;
+
+const HELM_VALUES_JSONSCHEMA: &str = r##""$schema": http://json-schema.org/draft-07/schema#
+title: Values
+type: object
+properties:
+  replicaCount:
+    type: integer
+    minimum: 0
+    default: 1
+  image:
+    type: object
+    properties:
+      repository:
+        type: string
+      tag:
+        type: string
+      pullPolicy:
+        enum:
+        - Always
+        - IfNotPresent
+        - Never
+    required:
+    - repository
+  service:
+    type: object
+    properties:
+      type:
+        enum:
+        - ClusterIP
+        - NodePort
+        - LoadBalancer
+      port:
+        type: integer
+    required:
+    - port
+  ingress:
+    type: object
+    properties:
+      enabled:
+        type: boolean
+      className:
+        type: string
+      hosts:
+        type: array
+        items:
+          type: object
+          properties:
+            host:
+              type: string
+            paths:
+              type: array
+              items:
+                type: object
+                properties:
+                  path:
+                    type: string
+                  pathType:
+                    enum:
+                    - Exact
+                    - Prefix
+                    - ImplementationSpecific
+                required:
+                - path
+          required:
+          - host
+  resources:
+    type: object
+    properties:
+      limits:
+        "$ref": "#/definitions/Resources"
+      requests:
+        "$ref": "#/definitions/Resources"
+required:
+- image
+- service
+definitions:
+  Resources:
+    type: object
+    properties:
+      cpu:
+        type:
+        - string
+        - number
+      memory:
+        type: string
+"##;
+
+const GITHUB_ACTIONS_BASE_FILE: &str = r#"name: CI
+on:
+  push:
+    branches:
+      - main
+  pull_request: {}
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Build
+        run: make build
+"#;
+
+const GITHUB_ACTIONS_OVERLAY_FILE: &str = r#"on:
+  push:
+    tags:
+      - "v*"
+jobs:
+  # Publishes tagged commits once they build
+  release:
+    runs-on: ubuntu-latest
+    needs: build
+    if: startsWith(github.ref, 'refs/tags/')
+    steps:
+      - uses: actions/checkout@v4
+      - name: Release
+        run: make release
+"#;
+
+const GITHUB_ACTIONS_JSONSCHEMA: &str = r##""$schema": http://json-schema.org/draft-07/schema#
+title: Workflow
+type: object
+properties:
+  name:
+    type: string
+  on:
+    type:
+    - string
+    - array
+    - object
+  env:
+    "$ref": "#/definitions/Env"
+  permissions:
+    type:
+    - string
+    - object
+  jobs:
+    type: object
+    minProperties: 1
+    additionalProperties:
+      "$ref": "#/definitions/Job"
+required:
+- on
+- jobs
+definitions:
+  Env:
+    type: object
+    additionalProperties:
+      type:
+      - string
+      - number
+      - boolean
+  StringOrList:
+    type:
+    - string
+    - array
+    items:
+      type: string
+  Job:
+    type: object
+    properties:
+      name:
+        type: string
+      runs-on:
+        "$ref": "#/definitions/StringOrList"
+      needs:
+        "$ref": "#/definitions/StringOrList"
+      if:
+        type: string
+      env:
+        "$ref": "#/definitions/Env"
+      strategy:
+        type: object
+      steps:
+        type: array
+        minItems: 1
+        items:
+          "$ref": "#/definitions/Step"
+    required:
+    - runs-on
+    - steps
+  Step:
+    type: object
+    properties:
+      id:
+        type: string
+      name:
+        type: string
+      if:
+        type: string
+      uses:
+        type: string
+      run:
+        type: string
+      with:
+        "$ref": "#/definitions/Env"
+      env:
+        "$ref": "#/definitions/Env"
+    anyOf:
+    - required:
+      - uses
+    - required:
+      - run
+"##;
+
+const COMPOSE_BASE_FILE: &str = r#"services:
+  app:
+    image: nginx:1.27
+    restart: unless-stopped
+    ports:
+      - "8080:80"
+"#;
+
+const COMPOSE_OVERLAY_FILE: &str = r#"services:
+  app:
+    environment:
+      LOG_LEVEL: debug
+    # Serve the local files while developing
+    volumes:
+      - ./html:/usr/share/nginx/html:ro
+"#;
+
+const COMPOSE_JSONSCHEMA: &str = r##""$schema": http://json-schema.org/draft-07/schema#
+title: Compose
+type: object
+properties:
+  services:
+    type: object
+    minProperties: 1
+    additionalProperties:
+      "$ref": "#/definitions/Service"
+  volumes:
+    type: object
+  networks:
+    type: object
+required:
+- services
+definitions:
+  StringOrList:
+    type:
+    - string
+    - array
+    items:
+      type: string
+  Service:
+    type: object
+    properties:
+      image:
+        type: string
+      build:
+        type:
+        - string
+        - object
+      command:
+        "$ref": "#/definitions/StringOrList"
+      restart:
+        enum:
+        - "no"
+        - always
+        - on-failure
+        - unless-stopped
+      ports:
+        type: array
+        items:
+          type:
+          - string
+          - integer
+      environment:
+        type:
+        - object
+        - array
+      env_file:
+        "$ref": "#/definitions/StringOrList"
+      volumes:
+        type: array
+        items:
+          type: string
+      depends_on:
+        type:
+        - array
+        - object
+    anyOf:
+    - required:
+      - image
+    - required:
+      - build
+"##;
+
+const ARGOCD_BASE_FILE: &str = r#"apiVersion: argoproj.io/v1alpha1
+kind: Application
+metadata:
+  namespace: argocd
+  finalizers:
+    - resources-finalizer.argocd.argoproj.io
+spec:
+  project: default
+  destination:
+    server: https://kubernetes.default.svc
+    namespace: default"#;
+
+const ARGOCD_OVERLAY_FILE: &str = r#"metadata:
+  name: guestbook
+  labels:
+    name: guestbook
+spec:
+  source:
+    repoURL: "https://github.com/argoproj/argocd-example-apps.git"
+    targetRevision: HEAD
+    path: guestbook
+    helm:
+      namespace: guestbook
+      version: v3
+      passCredentials: false # If true then adds --pass-credentials to Helm commands to pass credentials to all domains
+      # Extra parameters to set (same as setting through values.yaml, but these take precedence)
+      parameters:
+      - name: "nginx-ingress.controller.service.annotations.external-dns\\.alpha\\.kubernetes\\.io/hostname"
+        value: mydomain.example.com
+      - name: "ingress.annotations.kubernetes\\.io/tls-acme"
+        value: "true"
+        forceString: true # ensures that value is treated as a string
+
+      # Use the contents of files as parameters (uses Helm's --set-file)
+      fileParameters:
+      - name: config
+        path: files/config.json
+
+      # Release name override (defaults to application name)
+      releaseName: guestbook
+
+      # Helm values files for overriding values in the helm chart
+      # The path is relative to the spec.source.path directory defined above
+      valueFiles:
+      - values-prod.yaml;
+
+      # Ignore locally missing valueFiles when installing Helm chart. Defaults to false
+      ignoreMissingValueFiles: false
+
+      # Values file as block file. Prefer to use valuesObject if possible (see below)
+      values: |
+        ingress:
+          enabled: true
+          path: /
+          hosts:
+            - mydomain.example.com
+          annotations:
+            kubernetes.io/ingress.class: nginx
+            kubernetes.io/tls-acme: "true"
+          labels: {}
+          tls:
+            - secretName: mydomain-tls
+              hosts:
+                - mydomain.example.com
+
+      # Values file as block file. This takes precedence over values
+      valuesObject:
+        ingress:
+          enabled: true
+          path: /
+          hosts:
+            - mydomain.example.com
+          annotations:
+            kubernetes.io/ingress.class: nginx
+            kubernetes.io/tls-acme: "true"
+          labels: {}
+          tls:
+            - secretName: mydomain-tls
+              hosts:
+                - mydomain.example.com"#;
+
+const ARGOCD_JSONSCHEMA: &str = r##""$schema": http://json-schema.org/draft-06/schema#
+"$ref": "#/definitions/ArgoCDApplication"
+definitions:
+  ArgoCDApplication:
+    type: object
+    additionalProperties: false
+    properties:
+      apiVersion:
+        const: argoproj.io/v1alpha1
+      kind:
+        const: Application
+      metadata:
+        "$ref": "#/definitions/Metadata"
+      spec:
+        "$ref": "#/definitions/Spec"
+    required:
+    - apiVersion
+    - kind
+    - metadata
+    - spec
+    title: ArgoCDApplication
+  Metadata:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      namespace:
+        type: string
+        default: default
+      finalizers:
+        type: array
+        items:
+          type: string
+      labels:
+        "$ref": "#/definitions/MetadataLabels"
+      annotations:
+        "$ref": "#/definitions/MetadataLabels"
+    required:
+    - name
+    title: Metadata
+  MetadataLabels:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+    required:
+    - name
+    title: MetadataLabels
+  Spec:
+    type: object
+    additionalProperties: false
+    properties:
+      project:
+        type: string
+      source:
+        "$ref": "#/definitions/PurpleSource"
+      sources:
+        type: array
+        items:
+          "$ref": "#/definitions/SourceElement"
+      destination:
+        "$ref": "#/definitions/Destination"
+      info:
+        type: array
+        items:
+          "$ref": "#/definitions/Info"
+      syncPolicy:
+        "$ref": "#/definitions/SyncPolicy"
+      ignoreDifferences:
+        type: array
+        items:
+          "$ref": "#/definitions/IgnoreDifference"
+      revisionHistoryLimit:
+        type: integer
+    required:
+    - destination
+    - project
+    title: Spec
+  Destination:
+    type: object
+    additionalProperties: false
+    properties:
+      server:
+        type: string
+        format: uri
+        qt-uri-protocols:
+        - https
+      namespace:
+        type: string
+    required:
+    - namespace
+    - server
+    title: Destination
+  IgnoreDifference:
+    type: object
+    additionalProperties: false
+    properties:
+      group:
+        type: string
+      kind:
+        type: string
+      jsonPointers:
+        type: array
+        items:
+          type: string
+      jqPathExpressions:
+        type: array
+        items:
+          type: string
+      managedFieldsManagers:
+        type: array
+        items:
+          type: string
+      name:
+        type: string
+      namespace:
+        type: string
+    required:
+    - kind
+    title: IgnoreDifference
+  Info:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      value:
+        type: string
+        qt-uri-protocols:
+        - https
+    required:
+    - name
+    - value
+    title: Info
+  PurpleSource:
+    type: object
+    additionalProperties: false
+    properties:
+      repoURL:
+        type: string
+        format: uri
+        qt-uri-protocols:
+        - https
+        qt-uri-extensions:
+        - ".git"
+      targetRevision:
+        type: string
+      path:
+        type: string
+      chart:
+        type: string
+      helm:
+        "$ref": "#/definitions/Helm"
+      kustomize:
+        "$ref": "#/definitions/Kustomize"
+      directory:
+        "$ref": "#/definitions/Directory"
+      plugin:
+        "$ref": "#/definitions/Plugin"
+    required:
+    - repoURL
+    - targetRevision
+    title: PurpleSource
+  Directory:
+    type: object
+    additionalProperties: false
+    properties:
+      recurse:
+        type: boolean
+      jsonnet:
+        "$ref": "#/definitions/Jsonnet"
+      exclude:
+        type: string
+      include:
+        type: string
+    required:
+    - exclude
+    - include
+    - jsonnet
+    - recurse
+    title: Directory
+  Jsonnet:
+    type: object
+    additionalProperties: false
+    properties:
+      extVars:
+        type: array
+        items:
+          "$ref": "#/definitions/EXTVar"
+      tlas:
+        type: array
+        items:
+          "$ref": "#/definitions/EXTVar"
+    required:
+    - extVars
+    - tlas
+    title: Jsonnet
+  EXTVar:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      value:
+        type: string
+      code:
+        type: boolean
+    required:
+    - name
+    - value
+    title: EXTVar
+  Helm:
+    type: object
+    additionalProperties: false
+    properties:
+      passCredentials:
+        type: boolean
+      parameters:
+        type: array
+        items:
+          "$ref": "#/definitions/HelmParameter"
+      fileParameters:
+        type: array
+        items:
+          "$ref": "#/definitions/FileParameter"
+      releaseName:
+        type: string
+      valueFiles:
+        type: array
+        items:
+          type: string
+      ignoreMissingValueFiles:
+        type: boolean
+      values:
+        type: string
+      valuesObject:
+        "$ref": "#/definitions/ValuesObject"
+      skipCrds:
+        type: boolean
+      version:
+        type: string
+      kubeVersion:
+        type: string
+      apiVersions:
+        type: array
+        items:
+          type: string
+      namespace:
+        type: string
+    required:
+    - namespace
+    - version
+    title: Helm
+  FileParameter:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      path:
+        type: string
+    required:
+    - name
+    - path
+    title: FileParameter
+  HelmParameter:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      value:
+        type: string
+      forceString:
+        type: boolean
+    required:
+    - name
+    - value
+    title: HelmParameter
+  ValuesObject:
+    type: object
+    additionalProperties: false
+    properties:
+      ingress:
+        "$ref": "#/definitions/Ingress"
+    required:
+    - ingress
+    title: ValuesObject
+  Ingress:
+    type: object
+    additionalProperties: false
+    properties:
+      enabled:
+        type: boolean
+      path:
+        type: string
+      hosts:
+        type: array
+        items:
+          type: string
+      annotations:
+        "$ref": "#/definitions/IngressAnnotations"
+      labels:
+        "$ref": "#/definitions/IngressLabels"
+      tls:
+        type: array
+        items:
+          "$ref": "#/definitions/Tl"
+    required:
+    - annotations
+    - enabled
+    - hosts
+    - labels
+    - path
+    - tls
+    title: Ingress
+  IngressAnnotations:
+    type: object
+    additionalProperties: false
+    properties:
+      kubernetes.io/ingress.class:
+        type: string
+      kubernetes.io/tls-acme:
+        type: string
+        format: boolean
+    required:
+    - kubernetes.io/ingress.class
+    - kubernetes.io/tls-acme
+    title: IngressAnnotations
+  IngressLabels:
+    type: object
+    additionalProperties: false
+    title: IngressLabels
+  Tl:
+    type: object
+    additionalProperties: false
+    properties:
+      secretName:
+        type: string
+      hosts:
+        type: array
+        items:
+          type: string
+    required:
+    - hosts
+    - secretName
+    title: Tl
+  Kustomize:
+    type: object
+    additionalProperties: false
+    properties:
+      version:
+        type: string
+      namePrefix:
+        type: string
+      nameSuffix:
+        type: string
+      commonLabels:
+        "$ref": "#/definitions/CommonLabels"
+      commonAnnotations:
+        "$ref": "#/definitions/CommonAnnotations"
+      commonAnnotationsEnvsubst:
+        type: boolean
+      forceCommonLabels:
+        type: boolean
+      forceCommonAnnotations:
+        type: boolean
+      images:
+        type: array
+        items:
+          type: string
+      namespace:
+        type: string
+      replicas:
+        type: array
+        items:
+          "$ref": "#/definitions/Replica"
+      components:
+        type: array
+        items:
+          type: string
+      patches:
+        type: array
+        items:
+          "$ref": "#/definitions/Patch"
+      kubeVersion:
+        type: string
+      apiVersions:
+        type: array
+        items:
+          type: string
+    required:
+    - apiVersions
+    - commonAnnotations
+    - commonAnnotationsEnvsubst
+    - commonLabels
+    - components
+    - forceCommonAnnotations
+    - forceCommonLabels
+    - images
+    - kubeVersion
+    - namePrefix
+    - nameSuffix
+    - namespace
+    - patches
+    - replicas
+    - version
+    title: Kustomize
+  CommonAnnotations:
+    type: object
+    additionalProperties: false
+    properties:
+      beep:
+        type: string
+    required:
+    - beep
+    title: CommonAnnotations
+  CommonLabels:
+    type: object
+    additionalProperties: false
+    properties:
+      foo:
+        type: string
+    required:
+    - foo
+    title: CommonLabels
+  Patch:
+    type: object
+    additionalProperties: false
+    properties:
+      target:
+        "$ref": "#/definitions/Target"
+      patch:
+        type: string
+    required:
+    - patch
+    - target
+    title: Patch
+  Target:
+    type: object
+    additionalProperties: false
+    properties:
+      kind:
+        type: string
+      name:
+        type: string
+    required:
+    - kind
+    - name
+    title: Target
+  Replica:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      count:
+        type: integer
+    required:
+    - count
+    - name
+    title: Replica
+  Plugin:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      env:
+        type: array
+        items:
+          "$ref": "#/definitions/Info"
+      parameters:
+        type: array
+        items:
+          "$ref": "#/definitions/PluginParameter"
+    required:
+    - env
+    - name
+    - parameters
+    title: Plugin
+  PluginParameter:
+    type: object
+    additionalProperties: false
+    properties:
+      name:
+        type: string
+      string:
+        type: string
+      array:
+        type: array
+        items:
+          type: string
+      map:
+        "$ref": "#/definitions/Map"
+    required:
+    - name
+    title: PluginParameter
+  Map:
+    type: object
+    additionalProperties: false
+    properties:
+      param-name:
+        type: string
+    required:
+    - param-name
+    title: Map
+  SourceElement:
+    type: object
+    additionalProperties: false
+    properties:
+      repoURL:
+        type: string
+        format: uri
+        qt-uri-protocols:
+        - https
+        qt-uri-extensions:
+        - ".git"
+      targetRevision:
+        type: string
+      path:
+        type: string
+      ref:
+        type: string
+    required:
+    - path
+    - ref
+    - repoURL
+    - targetRevision
+    title: SourceElement
+  SyncPolicy:
+    type: object
+    additionalProperties: false
+    properties:
+      automated:
+        "$ref": "#/definitions/Automated"
+      syncOptions:
+        type: array
+        items:
+          type: string
+      managedNamespaceMetadata:
+        "$ref": "#/definitions/ManagedNamespaceMetadata"
+      retry:
+        "$ref": "#/definitions/Retry"
+    required:
+    - automated
+    - managedNamespaceMetadata
+    - retry
+    - syncOptions
+    title: SyncPolicy
+  Automated:
+    type: object
+    additionalProperties: false
+    properties:
+      prune:
+        type: boolean
+      selfHeal:
+        type: boolean
+      allowEmpty:
+        type: boolean
+    required:
+    - allowEmpty
+    - prune
+    - selfHeal
+    title: Automated
+  ManagedNamespaceMetadata:
+    type: object
+    additionalProperties: false
+    properties:
+      labels:
+        "$ref": "#/definitions/ManagedNamespaceMetadataLabels"
+      annotations:
+        "$ref": "#/definitions/ManagedNamespaceMetadataAnnotations"
+    required:
+    - annotations
+    - labels
+    title: ManagedNamespaceMetadata
+  ManagedNamespaceMetadataAnnotations:
+    type: object
+    additionalProperties: false
+    properties:
+      the:
+        type: string
+      applies:
+        type: string
+      annotations:
+        type: string
+    required:
+    - annotations
+    - applies
+    - the
+    title: ManagedNamespaceMetadataAnnotations
+  ManagedNamespaceMetadataLabels:
+    type: object
+    additionalProperties: false
+    properties:
+      any:
+        type: string
+      you:
+        type: string
+    required:
+    - any
+    - you
+    title: ManagedNamespaceMetadataLabels
+  Retry:
+    type: object
+    additionalProperties: false
+    properties:
+      limit:
+        type: integer
+      backoff:
+        "$ref": "#/definitions/Backoff"
+    required:
+    - backoff
+    - limit
+    title: Retry
+  Backoff:
+    type: object
+    additionalProperties: false
+    properties:
+      duration:
+        type: string
+      factor:
+        type: integer
+      maxDuration:
+        type: string
+    required:
+    - duration
+    - factor
+    - maxDuration"##;
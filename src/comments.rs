@@ -0,0 +1,381 @@
+use crate::merge::Moves;
+use std::collections::HashMap;
+
+/// Comments of a yaml document, attached to the path of the key or array item they belong to.
+/// Array items are addressed with `$<index>` path segments, like `File::insert` does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Comments {
+    entries: HashMap<Vec<String>, NodeComments>,
+    /// Comments after the last node of the document
+    footer: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct NodeComments {
+    /// Comment lines, and single blank lines separating them, preceding the node
+    before: Vec<String>,
+    /// Comment at the end of the line the node starts on
+    trailing: Option<String>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.footer.is_empty()
+    }
+
+    /// Adds a comment line before the node at `path`
+    pub fn push_before(&mut self, path: Vec<String>, comment: &str) {
+        let entry = self.entries.entry(path).or_default();
+        entry.before.push(format!("# {}", comment));
+    }
+
+    /// Extracts the comments of a single yaml document
+    pub fn parse(source: &str) -> Self {
+        let mut comments = Comments::default();
+        let mut tracker = PathTracker::default();
+        let mut pending: Vec<String> = Vec::new();
+        let mut seen_content = false;
+        for line in source.lines() {
+            if tracker.in_block_scalar(line) {
+                continue;
+            }
+            if tracker.take_block_scalar_blank() {
+                pending.push(String::new());
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                if pending.last().map_or(seen_content, |last| !last.is_empty()) {
+                    pending.push(String::new());
+                }
+                continue;
+            }
+            if trimmed.starts_with('#') {
+                pending.push(trimmed.to_owned());
+                continue;
+            }
+            if is_document_marker(trimmed) {
+                continue;
+            }
+            seen_content = true;
+            let node = tracker.track(line);
+            if let Some(first) = node.paths.first() {
+                if pending.iter().any(|line| !line.is_empty()) {
+                    while pending.last().is_some_and(String::is_empty) {
+                        pending.pop();
+                    }
+                    comments.entries.entry(first.clone()).or_default().before =
+                        std::mem::take(&mut pending);
+                } else {
+                    pending.clear();
+                }
+            }
+            if let (Some(last), Some(comment)) = (node.paths.last(), node.comment) {
+                comments.entries.entry(last.clone()).or_default().trailing = Some(comment);
+            }
+        }
+        pending.retain(|line| !line.is_empty());
+        comments.footer = pending;
+        comments
+    }
+
+    /// Extracts the comments of every document of a yaml stream
+    pub fn parse_documents(source: &str) -> Vec<Self> {
+        split_documents(source)
+            .iter()
+            .map(|(_, document)| Comments::parse(document))
+            .collect()
+    }
+
+    /// Adds the comments of an overlay that was merged, following array items to where the
+    /// merge put them, see `Moves`. Comments of removed items are dropped, and so are the
+    /// comments ending the line of values the overlay changed. Overlay comments take precedence
+    /// over the comments of the keys they touch.
+    pub(crate) fn merge(&mut self, overlay: Comments, moves: &Moves) {
+        let mut entries = HashMap::new();
+        for (path, mut comments) in self.entries.drain() {
+            if moves.changed(&path) {
+                comments.trailing = None;
+            }
+            if let Some(path) = moves.base_path(&path) {
+                entries.insert(path, comments);
+            }
+        }
+        self.entries = entries;
+        for (path, comments) in overlay.entries {
+            let Some(path) = moves.overlay_path(&path) else {
+                continue;
+            };
+            let entry = self.entries.entry(path).or_default();
+            if !comments.before.is_empty() {
+                entry.before = comments.before;
+            }
+            if comments.trailing.is_some() {
+                entry.trailing = comments.trailing;
+            }
+        }
+        if !overlay.footer.is_empty() {
+            self.footer = overlay.footer;
+        }
+    }
+
+    /// Inserts the comments into a yaml document as emitted by `yaml::emit`
+    pub(crate) fn apply(&self, yaml: &str) -> String {
+        let mut tracker = PathTracker::default();
+        let mut out = String::new();
+        for line in yaml.lines() {
+            if is_document_marker(line.trim()) {
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            }
+            let node = tracker.track(line);
+            let indent = &line[..line.len() - line.trim_start().len()];
+            for path in &node.paths {
+                if let Some(comments) = self.entries.get(path) {
+                    for comment in &comments.before {
+                        if !comment.is_empty() {
+                            out.push_str(indent);
+                            out.push_str(comment);
+                        }
+                        out.push('\n');
+                    }
+                }
+            }
+            out.push_str(line);
+            let trailing = node
+                .paths
+                .last()
+                .and_then(|path| self.entries.get(path))
+                .and_then(|comments| comments.trailing.as_ref());
+            if let Some(trailing) = trailing {
+                out.push(' ');
+                out.push_str(trailing);
+            }
+            out.push('\n');
+        }
+        for comment in &self.footer {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        // emitted documents don't end with a newline
+        out.pop();
+        out
+    }
+}
+
+/// Splits a yaml stream into its documents, each with the number of the line it starts on
+pub(crate) fn split_documents(source: &str) -> Vec<(usize, String)> {
+    let mut documents: Vec<(usize, String)> = vec![(1, String::new())];
+    for (i, line) in source.lines().enumerate() {
+        if line == "..." {
+            continue;
+        }
+        if is_document_marker(line) {
+            documents.push((i + 1, String::new()));
+        }
+        let (_, document) = documents.last_mut().expect("there is always a document");
+        document.push_str(line);
+        document.push('\n');
+    }
+    // comments before the first document marker belong to the first document
+    if documents.len() > 1 && !has_content(&documents[0].1) {
+        let (first_line, header) = documents.remove(0);
+        documents[0].0 = first_line;
+        documents[0].1.insert_str(0, &header);
+    }
+    documents
+}
+
+pub(crate) fn is_document_marker(line: &str) -> bool {
+    line == "---" || line.starts_with("--- ")
+}
+
+fn has_content(document: &str) -> bool {
+    document.lines().any(|line| {
+        let trimmed = line.trim();
+        !trimmed.is_empty() && !trimmed.starts_with('#') && !is_document_marker(trimmed)
+    })
+}
+
+struct Frame {
+    indent: usize,
+    segment: String,
+    is_item: bool,
+    /// Number of sequence items seen under this node
+    items: usize,
+}
+
+/// Nodes starting on a line, outermost first, and the comment ending the line
+pub(crate) struct TrackedLine {
+    pub(crate) paths: Vec<Vec<String>>,
+    pub(crate) comment: Option<String>,
+}
+
+/// Follows the path of the current node through the lines of a block style yaml document
+#[derive(Default)]
+pub(crate) struct PathTracker {
+    stack: Vec<Frame>,
+    root_items: usize,
+    /// Indentation of the line that opened the block scalar being read, if any
+    block_scalar: Option<usize>,
+    block_scalar_blank: bool,
+}
+
+impl PathTracker {
+    /// Whether the line is part of a block scalar, whose `#` are content and not comments
+    pub(crate) fn in_block_scalar(&mut self, line: &str) -> bool {
+        let Some(indent) = self.block_scalar else {
+            return false;
+        };
+        if line.trim().is_empty() {
+            self.block_scalar_blank = true;
+            return true;
+        }
+        if indentation(line) > indent {
+            self.block_scalar_blank = false;
+            return true;
+        }
+        self.block_scalar = None;
+        false
+    }
+
+    /// Whether the block scalar that just ended was followed by a blank line
+    fn take_block_scalar_blank(&mut self) -> bool {
+        std::mem::take(&mut self.block_scalar_blank)
+    }
+
+    pub(crate) fn track(&mut self, line: &str) -> TrackedLine {
+        let indent = indentation(line);
+        let mut column = indent;
+        let mut rest = &line[indent..];
+        let mut paths = Vec::new();
+        while rest == "-" || rest.starts_with("- ") {
+            while self
+                .stack
+                .last()
+                .is_some_and(|top| top.indent > column || (top.indent == column && top.is_item))
+            {
+                self.stack.pop();
+            }
+            let items = match self.stack.last_mut() {
+                Some(parent) => &mut parent.items,
+                None => &mut self.root_items,
+            };
+            let segment = format!("${}", items);
+            *items += 1;
+            self.stack.push(Frame {
+                indent: column,
+                segment,
+                is_item: true,
+                items: 0,
+            });
+            paths.push(self.path());
+            let after = &rest[1..];
+            let value = after.trim_start();
+            column += 1 + after.len() - value.len();
+            rest = value;
+        }
+        let value = match split_key(rest) {
+            Some((key, value)) => {
+                while self.stack.last().is_some_and(|top| top.indent >= column) {
+                    self.stack.pop();
+                }
+                self.stack.push(Frame {
+                    indent: column,
+                    segment: key,
+                    is_item: false,
+                    items: 0,
+                });
+                paths.push(self.path());
+                value
+            }
+            None => rest,
+        };
+        let (value, comment) = split_comment(value);
+        if value.starts_with('|') || value.starts_with('>') {
+            self.block_scalar = Some(indent);
+        }
+        TrackedLine {
+            paths,
+            comment: comment.map(str::to_owned),
+        }
+    }
+
+    fn path(&self) -> Vec<String> {
+        self.stack
+            .iter()
+            .map(|frame| frame.segment.clone())
+            .collect()
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Splits `key: value` into the unquoted key and the value
+fn split_key(s: &str) -> Option<(String, &str)> {
+    let (key, after) = if s.starts_with('"') || s.starts_with('\'') {
+        let end = quoted_end(s)?;
+        let quoted = &s[..end];
+        let key = if s.starts_with('"') {
+            serde_json::from_str(quoted).unwrap_or_else(|_| quoted[1..end - 1].to_owned())
+        } else {
+            quoted[1..end - 1].replace("''", "'")
+        };
+        (key, &s[end..])
+    } else {
+        let end = s
+            .char_indices()
+            .find(|&(i, c)| c == ':' && s[i + 1..].chars().next().is_none_or(char::is_whitespace))
+            .map(|(i, _)| i)?;
+        let key = &s[..end];
+        if key.contains(" #") || key.starts_with(['#', '{', '[', '&', '*', '!', '?']) {
+            return None;
+        }
+        (key.trim_end().to_owned(), &s[end..])
+    };
+    let after = after.strip_prefix(':')?;
+    if !after.is_empty() && !after.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((key, after.trim_start()))
+}
+
+/// Returns the byte index just after the closing quote of a quoted scalar starting `s`
+fn quoted_end(s: &str) -> Option<usize> {
+    let quote = s.chars().next()?;
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            if quote == '\'' && s[i + 1..].starts_with('\'') {
+                chars.next();
+                continue;
+            }
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Splits a value from the comment ending its line
+fn split_comment(value: &str) -> (&str, Option<&str>) {
+    let start = if value.starts_with('"') || value.starts_with('\'') {
+        quoted_end(value).unwrap_or(value.len())
+    } else {
+        0
+    };
+    if value.starts_with('#') {
+        return ("", Some(value));
+    }
+    let comment = value[start..]
+        .char_indices()
+        .find(|&(i, c)| c == '#' && value[start..][..i].ends_with([' ', '\t']))
+        .map(|(i, _)| start + i);
+    match comment {
+        Some(i) => (value[..i].trim_end(), Some(&value[i..])),
+        None => (value, None),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error;
+use crate::file::{Error, File, Format};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the project config file
+pub const CONFIG_FILE: &str = "overl.toml";
+
+/// Contents of `overl.toml`. Paths are relative to the folder holding it, anything left out
+/// takes its default value.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Config {
+    /// Project name, stored in the state file
+    pub name: String,
+    /// Folder holding a folder per template
+    pub templates: PathBuf,
+    /// Name of the folder holding the overlays in each template folder
+    pub overlays: String,
+    /// State file written by `sync`
+    pub state: PathBuf,
+    /// Folder rendered implementations are written to
+    pub output: PathBuf,
+    /// Format files added to templates are written in
+    pub format: Format,
+}
+
+impl Default for Config {
+    /// The layout projects had before they were configurable
+    fn default() -> Self {
+        Config {
+            name: "gitcomet".to_owned(),
+            templates: PathBuf::from("gitcomet/templates"),
+            overlays: "overlays".to_owned(),
+            state: PathBuf::from("gitcomet/gitcomet.gtcstate"),
+            output: PathBuf::from("gitcomet/output"),
+            format: Format::Yaml,
+        }
+    }
+}
+
+impl Config {
+    pub fn to_toml_string(&self) -> String {
+        toml::to_string(self).expect("a config is always serializable")
+    }
+}
+
+/// A project, its config and the folder the config paths are relative to
+#[derive(Clone, Debug)]
+pub struct Project {
+    root: PathBuf,
+    config: Config,
+}
+
+impl Project {
+    pub fn new<P: AsRef<Path>>(root: P, config: Config) -> Self {
+        Project {
+            root: root.as_ref().to_path_buf(),
+            config,
+        }
+    }
+
+    /// Finds the project `dir` belongs to by looking for `overl.toml` in it, then in its
+    /// parents. Without one, `dir` is a project with the default config.
+    pub fn discover<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let absolute = dir.canonicalize().map_err(|source| Error::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        // kept relative to `dir` so that paths in messages stay short
+        let mut root = dir.to_path_buf();
+        for folder in absolute.ancestors() {
+            if folder.join(CONFIG_FILE).is_file() {
+                return Project::load(root);
+            }
+            root = if root == Path::new(".") {
+                PathBuf::from("..")
+            } else {
+                root.join("..")
+            };
+        }
+        Ok(Project::new(dir, Config::default()))
+    }
+
+    /// Reads the config of the project in `root`
+    pub fn load<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        let root = root.as_ref();
+        let config_file = root.join(CONFIG_FILE);
+        let config = File::load(&config_file)?.into_data();
+        let config = serde_json::from_value(config).map_err(|source| Error::Json {
+            path: config_file,
+            source,
+        })?;
+        Ok(Project::new(root, config))
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+    pub fn config_file(&self) -> PathBuf {
+        self.root.join(CONFIG_FILE)
+    }
+    pub fn templates_dir(&self) -> PathBuf {
+        self.root.join(&self.config.templates)
+    }
+    pub fn template_dir(&self, template: &str) -> PathBuf {
+        self.templates_dir().join(template)
+    }
+    pub fn overlays_dir(&self, template: &str) -> PathBuf {
+        self.template_dir(template).join(&self.config.overlays)
+    }
+    pub fn state_file(&self) -> PathBuf {
+        self.root.join(&self.config.state)
+    }
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join(&self.config.output)
+    }
+}
+
+/// Checks that a template or overlay name is a plain file name, so that it can't point outside
+/// of the folder it is created in
+pub(crate) fn check_name(name: &str) -> Result<(), error::Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(error::Error::InvalidName {
+            name: name.to_owned(),
+        });
+    }
+    Ok(())
+}
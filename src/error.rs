@@ -0,0 +1,95 @@
+use crate::file;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Errors of working on a project: its templates, overlays, implementations and state, on top
+/// of the errors of reading, converting and writing the files they are made of
+#[derive(Debug)]
+pub enum Error {
+    /// A file that could not be read, converted or written
+    File(file::Error),
+    /// A json schema that can't be compiled
+    InvalidSchema { path: PathBuf, message: String },
+    /// A file, or a render of it, that its schema doesn't accept
+    Invalid { path: PathBuf, message: String },
+    /// The state file doesn't hold what was asked for
+    State { path: PathBuf, message: String },
+    /// A template that isn't in the built-in catalogue
+    UnknownCatalogueTemplate { name: String },
+    /// Files that are in the way of the ones `init` writes
+    Modified { paths: Vec<PathBuf> },
+    /// A file that the base and overlay extracted from it don't render back to
+    Extract { path: PathBuf, message: String },
+    /// A document of an overlay, counted from 0, that targets no document of the base
+    UnmatchedDocument { path: PathBuf, index: usize },
+    /// A template or overlay name that isn't a plain file name
+    InvalidName { name: String },
+    /// A template or overlay that is already there, and is never overwritten
+    AlreadyExists { path: PathBuf },
+    /// An overlay two of the files a template is created from would both be written to
+    DuplicateOverlay { path: PathBuf },
+}
+
+impl From<file::Error> for Error {
+    fn from(error: file::Error) -> Self {
+        Error::File(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::File(error) => write!(f, "{}", error),
+            Error::InvalidSchema { path, message } => {
+                write!(f, "{}: invalid schema: {}", path.display(), message)
+            }
+            Error::Invalid { path, message } => {
+                write!(
+                    f,
+                    "{}: doesn't match the schema: {}",
+                    path.display(),
+                    message
+                )
+            }
+            Error::State { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::UnknownCatalogueTemplate { name } => {
+                write!(f, "unknown template {}, see init --list-templates", name)
+            }
+            Error::Modified { paths } => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "modified files are in the way, use --force to overwrite: {}",
+                    paths.join(", ")
+                )
+            }
+            Error::Extract { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::UnmatchedDocument { path, index } => write!(
+                f,
+                "{}: document {} targets no document of the base, set its kind and metadata.name",
+                path.display(),
+                index + 1
+            ),
+            Error::InvalidName { name } => write!(
+                f,
+                "invalid name {:?}, names can't be empty, hold path separators or be `..`",
+                name
+            ),
+            Error::AlreadyExists { path } => write!(f, "{}: already exists", path.display()),
+            Error::DuplicateOverlay { path } => write!(
+                f,
+                "{}: given by more than one file, rename one of them",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::File(error) => error.source(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::config::Project;
+use crate::error::Error;
+use crate::file::{self, File};
+use crate::init;
+use crate::scaffold;
+use crate::schema;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Returns what every value has in common: the keys all objects have, with the common part of
+/// their values, and values that are equal everywhere. Arrays are only common when equal, as no
+/// array merge strategy can turn one array into any other. `None` when nothing is common.
+pub fn common(values: &[&Value]) -> Option<Value> {
+    let (first, rest) = values.split_first()?;
+    if let Value::Object(first) = first {
+        let objects: Option<Vec<&Map<String, Value>>> =
+            rest.iter().map(|value| value.as_object()).collect();
+        if let Some(objects) = objects {
+            let mut common_object = Map::new();
+            for (key, value) in first {
+                let mut children = vec![value];
+                children.extend(objects.iter().filter_map(|object| object.get(key)));
+                if children.len() != values.len() {
+                    continue;
+                }
+                if let Some(child) = common(&children) {
+                    common_object.insert(key.clone(), child);
+                }
+            }
+            return Some(Value::Object(common_object));
+        }
+    }
+    rest.iter()
+        .all(|value| *value == *first)
+        .then(|| (*first).clone())
+}
+
+/// Returns the overlay that turns `base` into `value` when merged into it: the keys of `value`
+/// that `base` doesn't have or has another value for, recursing into objects. `base` has to be
+/// a common part of `value`, see `common`.
+pub fn difference(base: &Value, value: &Value) -> Value {
+    let (Value::Object(base), Value::Object(object)) = (base, value) else {
+        return value.clone();
+    };
+    let mut overlay = Map::new();
+    for (key, value) in object {
+        match base.get(key) {
+            Some(base_value) if base_value == value => {}
+            Some(base_value @ Value::Object(_)) if value.is_object() => {
+                let child = difference(base_value, value);
+                if child.as_object().is_some_and(|child| !child.is_empty()) {
+                    overlay.insert(key.clone(), child);
+                }
+            }
+            _ => {
+                overlay.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Value::Object(overlay)
+}
+
+/// Creates a template from files that render the same kind of thing: their common part is the
+/// base, the difference of each file an overlay named after it, and the schema is inferred from
+/// the files. Each file is checked to render back from the base and its overlay with
+/// `File::merge` before anything is written, see `check_rendering`. Files have to hold a single
+/// document: a yaml stream fails with `file::Error::MultipleDocuments`.
+pub fn extract_template(
+    project: &Project,
+    name: &str,
+    files: &[PathBuf],
+) -> Result<PathBuf, Error> {
+    let template_folder = init::new_template_folder(project, name)?;
+    let format = project.config().format;
+    let file_name = |stem: &str| format!("{}.{}", stem, format.name());
+
+    let documents = files
+        .iter()
+        .map(File::load)
+        .collect::<Result<Vec<File>, file::Error>>()?;
+    let values: Vec<&Value> = documents.iter().map(File::data).collect();
+    let base = common(&values).unwrap_or_else(|| Value::Object(Map::new()));
+
+    let mut written = vec![(
+        template_folder.join(file_name("base")),
+        File::new(base.clone()).to_format_string(format)?,
+    )];
+    let overlays_folder = project.overlays_dir(name);
+    for (path, value) in files.iter().zip(&values) {
+        let overlay = difference(&base, value);
+        check_rendering(path, &base, &overlay, value)?;
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let overlay_path = overlays_folder.join(file_name(&stem));
+        if written.iter().any(|(known, _)| *known == overlay_path) {
+            return Err(Error::DuplicateOverlay { path: overlay_path });
+        }
+        let content = File::new(overlay)
+            .to_format_string(format)
+            .map_err(|error| error.with_path(path))?;
+        written.push((overlay_path, content));
+    }
+    let schema = File::new(schema::infer(&values));
+    written.push((
+        template_folder.join(file_name("schema")),
+        schema.to_format_string(format)?,
+    ));
+    scaffold::write_files(written)?;
+    Ok(template_folder)
+}
+
+/// Checks that merging `overlay` into `base` gives back `value`, read from `path`. Only the data
+/// is compared: key order isn't, as the merge keeps the order of the base, taken from the first
+/// file, and neither are comments and formatting, which extracting doesn't keep.
+fn check_rendering(path: &Path, base: &Value, overlay: &Value, value: &Value) -> Result<(), Error> {
+    let mut rendered = File::new(base.clone());
+    rendered.merge(File::new(overlay.clone()));
+    if rendered.data() != value {
+        return Err(Error::Extract {
+            path: path.to_path_buf(),
+            message: "the extracted base and overlay don't render back to this file".to_owned(),
+        });
+    }
+    Ok(())
+}
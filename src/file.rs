@@ -1,48 +1,194 @@
-use jsonschema::ValidationError;
+use crate::comments::Comments;
+use crate::merge::{self, MergeStrategy};
+use crate::report::ValidationReport;
+use crate::schema;
+use crate::sources::Sources;
+use crate::yaml;
+use jsonschema::{ValidationError, Validator};
 use serde_json::Map;
 use serde_json::{Number, Value};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 use yaml_rust2::yaml::Hash;
-use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Errors that can occur while reading, converting or writing a `File`
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// The file is not valid yaml
+    Yaml {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The file is not valid json
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// The file is not valid toml
+    Toml {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A number that can't be represented in the target format, e.g. `.inf` in json
+    UnrepresentableNumber {
+        path: Option<PathBuf>,
+        value: String,
+    },
+    /// A mapping key that isn't a string
+    NonStringKey { path: Option<PathBuf>, key: String },
+    /// A single document was expected but the yaml stream contains several
+    MultipleDocuments { path: PathBuf, count: usize },
+    /// A value that has no equivalent in the target format, e.g. `null` in toml
+    Unrepresentable {
+        path: Option<PathBuf>,
+        format: &'static str,
+        reason: String,
+    },
+}
+
+impl Error {
+    /// Attaches the path of the file being converted to conversion errors
+    pub(crate) fn with_path(self, file_path: &Path) -> Self {
+        match self {
+            Error::UnrepresentableNumber { value, .. } => Error::UnrepresentableNumber {
+                path: Some(file_path.to_path_buf()),
+                value,
+            },
+            Error::NonStringKey { key, .. } => Error::NonStringKey {
+                path: Some(file_path.to_path_buf()),
+                key,
+            },
+            Error::Unrepresentable { format, reason, .. } => Error::Unrepresentable {
+                path: Some(file_path.to_path_buf()),
+                format,
+                reason,
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Yaml {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            Error::Json { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Toml {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            Error::UnrepresentableNumber { path, value } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "number {} can't be represented", value)
+            }
+            Error::NonStringKey { path, key } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "key {} is not a string", key)
+            }
+            Error::MultipleDocuments { path, count } => write!(
+                f,
+                "{}: expected a single document, found {}",
+                path.display(),
+                count
+            ),
+            Error::Unrepresentable {
+                path,
+                format,
+                reason,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "can't be represented as {}: {}", format, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 /// Wrapper for yaml_rust2::Yaml type. This is to be able to implement external traits.
 pub struct YamlType(pub Yaml);
 /// Wrapper for serde_json::Value type. This is to be able to implement external traits.
 pub struct JsonType(pub Value);
+/// Wrapper for toml::Value type. This is to be able to implement external traits.
+pub struct TomlType(pub toml::Value);
+
+impl TryFrom<YamlType> for JsonType {
+    type Error = Error;
 
-impl From<YamlType> for JsonType {
     /// Converts a YamlType into a JsonType
-    fn from(value: YamlType) -> Self {
+    fn try_from(value: YamlType) -> Result<Self, Self::Error> {
         match value.0 {
-            Yaml::Null => JsonType(Value::Null),
-            Yaml::String(str) => JsonType(Value::String(str)),
-            Yaml::Boolean(b) => JsonType(Value::Bool(b)),
-            Yaml::Integer(i) => JsonType(Value::Number(i.into())),
+            Yaml::Null => Ok(JsonType(Value::Null)),
+            Yaml::String(str) => Ok(JsonType(Value::String(str))),
+            Yaml::Boolean(b) => Ok(JsonType(Value::Bool(b))),
+            Yaml::Integer(i) => Ok(JsonType(Value::Number(i.into()))),
             Yaml::Real(r) => {
-                let real: f64 = r.parse().expect("could not parse float");
-                let num = Number::from_f64(real).expect("could not convert from f64 to number");
-                JsonType(Value::Number(num))
+                let num = r.parse::<f64>().ok().and_then(Number::from_f64).ok_or(
+                    Error::UnrepresentableNumber {
+                        path: None,
+                        value: r,
+                    },
+                )?;
+                Ok(JsonType(Value::Number(num)))
             }
             Yaml::Array(arr) => {
                 let mut new_arr: Vec<Value> = Vec::new();
                 for v in arr.into_iter() {
-                    let json_type = JsonType::from(YamlType(v));
+                    let json_type = JsonType::try_from(YamlType(v))?;
                     new_arr.push(json_type.0);
                 }
-                JsonType(Value::Array(new_arr))
+                Ok(JsonType(Value::Array(new_arr)))
             }
             Yaml::Hash(h) => {
                 let mut obj = Map::new();
                 for (key, val) in h.into_iter() {
-                    if let Some(key_as_string) = key.as_str() {
-                        let json_type = JsonType::from(YamlType(val));
-                        obj.insert(key_as_string.to_owned(), json_type.0);
-                    }
+                    let Yaml::String(key_as_string) = key else {
+                        return Err(Error::NonStringKey {
+                            path: None,
+                            key: format!("{:?}", key),
+                        });
+                    };
+                    let json_type = JsonType::try_from(YamlType(val))?;
+                    obj.insert(key_as_string, json_type.0);
                 }
-                JsonType(Value::Object(obj))
+                Ok(JsonType(Value::Object(obj)))
             }
-            _ => JsonType(Value::Null),
+            Yaml::Alias(_) | Yaml::BadValue => Err(Error::Unrepresentable {
+                path: None,
+                format: "json",
+                reason: "invalid value for its tag".to_owned(),
+            }),
         }
     }
 }
@@ -55,8 +201,7 @@ impl From<JsonType> for YamlType {
             Value::String(str) => YamlType(Yaml::String(str)),
             Value::Bool(b) => YamlType(Yaml::Boolean(b)),
             Value::Number(i) => {
-                if i.is_i64() || i.is_u64() {
-                    let int = i.as_i64().expect("could not convert to i64");
+                if let Some(int) = i.as_i64() {
                     YamlType(Yaml::Integer(int))
                 } else {
                     YamlType(Yaml::Real(i.to_string()))
@@ -82,60 +227,461 @@ impl From<JsonType> for YamlType {
     }
 }
 
-struct StackItem {
-    pub path: Vec<String>,
-    pub value: Value,
+impl TryFrom<TomlType> for JsonType {
+    type Error = Error;
+
+    /// Converts a TomlType into a JsonType. Datetimes become RFC 3339 strings.
+    fn try_from(value: TomlType) -> Result<Self, Self::Error> {
+        toml_to_json(value.0, false).map(JsonType)
+    }
+}
+
+/// Tag of toml datetimes read without losing data
+const DATETIME_TAG: &str = "!!timestamp";
+
+/// Converts a toml value to json. Datetimes become RFC 3339 strings, or with `lossless` values
+/// tagged `!!timestamp`, which are written back to toml as datetimes.
+fn toml_to_json(value: toml::Value, lossless: bool) -> Result<Value, Error> {
+    match value {
+        toml::Value::String(str) => Ok(Value::String(str)),
+        toml::Value::Integer(i) => Ok(Value::Number(i.into())),
+        toml::Value::Float(f) => {
+            let num = Number::from_f64(f).ok_or(Error::UnrepresentableNumber {
+                path: None,
+                value: f.to_string(),
+            })?;
+            Ok(Value::Number(num))
+        }
+        toml::Value::Boolean(b) => Ok(Value::Bool(b)),
+        toml::Value::Datetime(datetime) if lossless => Ok(yaml::tagged(
+            DATETIME_TAG,
+            Value::String(datetime.to_string()),
+        )),
+        toml::Value::Datetime(datetime) => Ok(Value::String(datetime.to_string())),
+        toml::Value::Array(arr) => {
+            let mut new_arr: Vec<Value> = Vec::new();
+            for v in arr.into_iter() {
+                new_arr.push(toml_to_json(v, lossless)?);
+            }
+            Ok(Value::Array(new_arr))
+        }
+        toml::Value::Table(table) => {
+            let mut obj = Map::new();
+            for (key, val) in table.into_iter() {
+                obj.insert(key, toml_to_json(val, lossless)?);
+            }
+            Ok(Value::Object(obj))
+        }
+    }
+}
+
+impl TryFrom<JsonType> for TomlType {
+    type Error = Error;
+
+    /// Converts a JsonType into a TomlType. Values tagged `!!timestamp` become datetimes. Fails
+    /// on `null`, which toml has no equivalent for.
+    fn try_from(value: JsonType) -> Result<Self, Self::Error> {
+        if let Some((DATETIME_TAG, Value::String(datetime))) = yaml::as_tagged(&value.0) {
+            if let Ok(datetime) = datetime.parse() {
+                return Ok(TomlType(toml::Value::Datetime(datetime)));
+            }
+        }
+        match value.0 {
+            Value::Null => Err(Error::Unrepresentable {
+                path: None,
+                format: "toml",
+                reason: "toml has no null value".to_owned(),
+            }),
+            Value::String(str) => Ok(TomlType(toml::Value::String(str))),
+            Value::Bool(b) => Ok(TomlType(toml::Value::Boolean(b))),
+            Value::Number(i) => {
+                if let Some(int) = i.as_i64() {
+                    Ok(TomlType(toml::Value::Integer(int)))
+                } else if i.is_f64() {
+                    Ok(TomlType(toml::Value::Float(i.as_f64().unwrap_or_default())))
+                } else {
+                    Err(Error::UnrepresentableNumber {
+                        path: None,
+                        value: i.to_string(),
+                    })
+                }
+            }
+            Value::Array(arr) => {
+                let mut new_arr: Vec<toml::Value> = Vec::new();
+                for v in arr.into_iter() {
+                    let toml_type = TomlType::try_from(JsonType(v))?;
+                    new_arr.push(toml_type.0);
+                }
+                Ok(TomlType(toml::Value::Array(new_arr)))
+            }
+            Value::Object(h) => {
+                let mut table = toml::Table::new();
+                for (key, val) in h.into_iter() {
+                    let toml_type = TomlType::try_from(JsonType(val))?;
+                    table.insert(key, toml_type.0);
+                }
+                Ok(TomlType(toml::Value::Table(table)))
+            }
+        }
+    }
+}
+
+/// How yaml documents are read by `File::load_documents_with`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadOptions {
+    /// Keeps comments so that `File::to_yaml_string` writes them back
+    pub comments: bool,
+    /// Reads yaml without losing data, see `yaml::load_lossless`, and toml datetimes as values
+    /// tagged `!!timestamp` rather than strings
+    pub lossless: bool,
+}
+
+/// Data formats a `File` can be read from and written to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// Supported extensions, in the order they are looked up by `find_file`
+    pub const EXTENSIONS: [&'static str; 4] = ["yaml", "yml", "json", "toml"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+            Format::Toml => "toml",
+        }
+    }
+
+    /// Returns the format matching the extension of a path
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(Format::Yaml),
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of some content. Json is also valid yaml so it is tried first, then
+    /// toml, and anything else is treated as yaml.
+    pub fn sniff(content: &str) -> Self {
+        if serde_json::from_str::<Value>(content).is_ok() {
+            Format::Json
+        } else if !content.trim().is_empty() && content.parse::<toml::Table>().is_ok() {
+            Format::Toml
+        } else {
+            Format::Yaml
+        }
+    }
+}
+
+/// Serializes documents as a multi-document yaml stream
+pub fn documents_to_yaml_string(documents: &[File]) -> String {
+    documents
+        .iter()
+        .map(File::to_yaml_string)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Serializes documents in the given format. Only yaml can hold more than one document.
+pub fn documents_to_format_string(documents: &[File], format: Format) -> Result<String, Error> {
+    match (format, documents) {
+        (Format::Yaml, documents) => Ok(documents_to_yaml_string(documents)),
+        (format, [document]) => document.to_format_string(format),
+        (format, documents) => Err(Error::Unrepresentable {
+            path: None,
+            format: format.name(),
+            reason: format!("expected a single document, found {}", documents.len()),
+        }),
+    }
+}
+
+/// Writes documents in the format matching the extension of the path, yaml if the extension is
+/// unknown. Only yaml can hold more than one document.
+pub fn save_documents<P: AsRef<Path>>(documents: &[File], path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    let format = Format::from_path(path).unwrap_or(Format::Yaml);
+    let content = documents_to_format_string(documents, format).map_err(|e| e.with_path(path))?;
+    write(path, content)
+}
+
+/// Finds `<stem>.<extension>` in a folder for any supported extension
+pub fn find_file(folder: &Path, stem: &str) -> Option<PathBuf> {
+    Format::EXTENSIONS
+        .iter()
+        .map(|extension| folder.join(format!("{}.{}", stem, extension)))
+        .find(|path| path.is_file())
 }
 
 /// Represents the contents of a file from a supported data format (yaml, json or toml)
 pub struct File {
     /// Contents of the file. For max compatibility with serde, the contents is always stored as
-    /// serde_json::Value
+    /// serde_json::Value. Object keys keep the order they were read in, and keys added by
+    /// merging or inserting are appended after them.
     data: Value,
+    /// Comments of the yaml source, only read when asked for
+    comments: Comments,
+    /// Lines of the yaml source the values were read from
+    sources: Sources,
 }
 
-
-
 impl File {
     pub fn new(data: Value) -> Self {
-        File { data }
+        File {
+            data,
+            comments: Comments::default(),
+            sources: Sources::default(),
+        }
+    }
+    pub fn with_comments(mut self, comments: Comments) -> Self {
+        self.comments = comments;
+        self
+    }
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+    pub fn with_sources(mut self, sources: Sources) -> Self {
+        self.sources = sources;
+        self
+    }
+    pub fn sources(&self) -> &Sources {
+        &self.sources
     }
     pub fn data(&self) -> &Value {
         &self.data
     }
-    pub fn from_json(path: &str) -> Self {
-        let content = fs::read_to_string(path).expect("Should have been able to read the file");
-        let json: Value = serde_json::from_str(&content).unwrap();
-        File::new(json)
+    pub fn into_data(self) -> Value {
+        self.data
+    }
+    /// Reads a file, choosing the parser from its extension, or from its contents if the
+    /// extension isn't one of a supported format.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = read_to_string(path)?;
+        let format = Format::from_path(path).unwrap_or_else(|| Format::sniff(&content));
+        let file = File::parse(&content, format, path)?;
+        if format == Format::Yaml {
+            return Ok(file.with_sources(Sources::parse(&content, path, 1)));
+        }
+        Ok(file)
+    }
+    /// Writes a file in the format matching its extension, yaml if the extension is unknown
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let format = Format::from_path(path).unwrap_or(Format::Yaml);
+        let content = self
+            .to_format_string(format)
+            .map_err(|e| e.with_path(path))?;
+        write(path, content)
+    }
+    /// Serializes the contents in the given format
+    pub fn to_format_string(&self, format: Format) -> Result<String, Error> {
+        match format {
+            Format::Yaml => Ok(self.to_yaml_string()),
+            Format::Json => Ok(self.to_json()),
+            Format::Toml => self.to_toml_string(),
+        }
+    }
+    fn parse(content: &str, format: Format, path: &Path) -> Result<Self, Error> {
+        match format {
+            Format::Yaml => File::parse_yaml(content, path),
+            Format::Json => File::parse_json(content, path),
+            Format::Toml => File::parse_toml(content, path, false),
+        }
+    }
+    pub fn from_json<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::parse_json(&read_to_string(path)?, path)
+    }
+    fn parse_json(content: &str, path: &Path) -> Result<Self, Error> {
+        let json: Value = serde_json::from_str(content).map_err(|source| Error::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(File::new(json))
     }
-    pub fn from_yaml<P: AsRef<Path>>(path: P) -> Self {
-        let content = fs::read_to_string(path).expect("Should have been able to read the file");
-        let yamls = YamlLoader::load_from_str(&content).unwrap();
-        let yaml = YamlType(yamls[0].clone());
-        let json: JsonType = yaml.into();
-        File::new(json.0)
+    pub fn from_yaml<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::parse_yaml(&read_to_string(path)?, path)
     }
+    /// Fails with `Error::MultipleDocuments` on multi-document streams, see
+    /// `from_yaml_documents`. An empty file is read as `null`.
+    fn parse_yaml(content: &str, path: &Path) -> Result<Self, Error> {
+        let mut documents = File::parse_yaml_documents(content, path)?;
+        match documents.len() {
+            0 => Ok(File::new(Value::Null)),
+            1 => Ok(documents.remove(0)),
+            count => Err(Error::MultipleDocuments {
+                path: path.to_path_buf(),
+                count,
+            }),
+        }
+    }
+    /// Reads every document of a yaml stream
+    pub fn from_yaml_documents<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, Error> {
+        let path = path.as_ref();
+        File::parse_yaml_documents(&read_to_string(path)?, path)
+    }
+    fn parse_yaml_documents(content: &str, path: &Path) -> Result<Vec<Self>, Error> {
+        let yamls = YamlLoader::load_from_str(content).map_err(|error| Error::Yaml {
+            path: path.to_path_buf(),
+            line: error.marker().line(),
+            column: error.marker().col() + 1,
+            message: error.info().to_owned(),
+        })?;
+        let mut documents = Vec::new();
+        for yaml in yamls {
+            let json = JsonType::try_from(YamlType(yaml)).map_err(|error| error.with_path(path))?;
+            documents.push(File::new(json.0));
+        }
+        Ok(documents)
+    }
+    /// Like `load`, but reads every document of yaml streams. Other formats always hold a
+    /// single document.
+    pub fn load_documents<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, Error> {
+        File::load_documents_with(path, &LoadOptions::default())
+    }
+    /// Like `load_documents`, but also keeps the comments of yaml documents so that
+    /// `to_yaml_string` writes them back
+    pub fn load_documents_with_comments<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, Error> {
+        let options = LoadOptions {
+            comments: true,
+            ..LoadOptions::default()
+        };
+        File::load_documents_with(path, &options)
+    }
+    /// Like `load_documents`, reading yaml documents as set by `options`
+    pub fn load_documents_with<P: AsRef<Path>>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<Vec<Self>, Error> {
+        let path = path.as_ref();
+        let content = read_to_string(path)?;
+        let format = Format::from_path(path).unwrap_or_else(|| Format::sniff(&content));
+        if format == Format::Toml {
+            return Ok(vec![File::parse_toml(&content, path, options.lossless)?]);
+        }
+        if format != Format::Yaml {
+            return Ok(vec![File::parse(&content, format, path)?]);
+        }
+        let mut documents = if options.lossless {
+            yaml::load_lossless(&content, path)?
+                .into_iter()
+                .map(File::new)
+                .collect()
+        } else {
+            File::parse_yaml_documents(&content, path)?
+        };
+        let sources = Sources::parse_documents(&content, path);
+        if sources.len() == documents.len() {
+            documents = documents
+                .into_iter()
+                .zip(sources)
+                .map(|(document, sources)| document.with_sources(sources))
+                .collect();
+        }
+        if !options.comments {
+            return Ok(documents);
+        }
+        let comments = Comments::parse_documents(&content);
+        if comments.len() != documents.len() {
+            return Ok(documents);
+        }
+        Ok(documents
+            .into_iter()
+            .zip(comments)
+            .map(|(document, comments)| document.with_comments(comments))
+            .collect())
+    }
+    /// Tagged values, see `yaml::tagged`, are written back with their tag
     pub fn to_yaml_string(&self) -> String {
-        let yaml_type: YamlType = JsonType(self.data.clone()).into();
-        let mut out_str = String::new();
-        {
-            let mut emitter = YamlEmitter::new(&mut out_str);
-            emitter.dump(&yaml_type.0).unwrap(); // dump the YAML object to a String
+        let out_str = yaml::emit(&self.data);
+        if self.comments.is_empty() {
+            out_str
+        } else {
+            self.comments.apply(&out_str)
         }
-        out_str
+    }
+    /// Reads a toml file. Inline tables are read like any other table and datetimes become
+    /// RFC 3339 strings, so writing the file back quotes them. `load_documents_with` keeps
+    /// them as datetimes when reading losslessly.
+    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::parse_toml(&read_to_string(path)?, path, false)
+    }
+    fn parse_toml(content: &str, path: &Path, lossless: bool) -> Result<Self, Error> {
+        let toml = content.parse::<toml::Value>().map_err(|error| {
+            let (line, column) = error
+                .span()
+                .map(|span| line_and_column(content, span.start))
+                .unwrap_or((0, 0));
+            Error::Toml {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message: error.message().trim_end().to_owned(),
+            }
+        })?;
+        let json = toml_to_json(toml, lossless).map_err(|error| error.with_path(path))?;
+        Ok(File::new(json))
+    }
+    /// Serializes the contents as toml. The contents must be an object without `null` values.
+    pub fn to_toml_string(&self) -> Result<String, Error> {
+        if let Some(pointer) = null_pointer(&self.data) {
+            let location = if pointer.is_empty() {
+                "the top level"
+            } else {
+                &pointer
+            };
+            return Err(Error::Unrepresentable {
+                path: None,
+                format: "toml",
+                reason: format!("toml has no null value, found at {}", location),
+            });
+        }
+        let toml_type = TomlType::try_from(JsonType(self.data.clone()))?;
+        let toml::Value::Table(table) = toml_type.0 else {
+            return Err(Error::Unrepresentable {
+                path: None,
+                format: "toml",
+                reason: "the top level of a toml document has to be a table".to_owned(),
+            });
+        };
+        toml::to_string_pretty(&table).map_err(|error| Error::Unrepresentable {
+            path: None,
+            format: "toml",
+            reason: error.to_string(),
+        })
     }
     pub fn to_json(&self) -> String {
         self.data.to_string()
     }
 
-    pub fn write(&self, path: &str) {
-        fs::write(path, self.to_json()).expect("Unable to write file");
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        write(path.as_ref(), self.to_json())
     }
-    pub fn write_yaml(&self, path: &str) {
-        fs::write(path, self.to_yaml_string()).expect("Unable to write file");
+    pub fn write_yaml<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        write(path.as_ref(), self.to_yaml_string())
+    }
+    pub fn write_toml<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let content = self.to_toml_string().map_err(|e| e.with_path(path))?;
+        write(path, content)
     }
 
+    /// Inserts a value at a path of keys, where `$<index>` segments address array items.
+    /// Inserting the `~delete` sentinel removes the value at the path instead.
     pub fn insert(&mut self, path: Vec<String>, value: Value) {
+        if value.as_str() == Some(merge::DELETE) {
+            self.remove(&path);
+            return;
+        }
         let mut json_obj = &mut self.data;
         if path.len() == 1 {
             if let Value::Object(node_to_update) = json_obj {
@@ -146,15 +692,12 @@ impl File {
             while i < path.len() - 1 {
                 let key = &path[i];
                 if let Value::Object(node) = json_obj {
-                    if path[i + 1].starts_with("$") && !node.contains_key(key) {
-                        node.insert(key.to_owned(), Value::Array(Vec::new()));
-                    } else if path[i + 1].starts_with("$") && !node[key].is_array() {
-                        node.remove(key);
-                        node.insert(key.clone(), Value::Array(Vec::new()));
-                    } else if !node.contains_key(key) {
-                        node.insert(key.clone(), Value::Object(Map::new()));
-                    } else if !node[key].is_object() && !path[i + 1].starts_with("$") {
-                        node.remove(key);
+                    // replacing in place keeps the position of the key
+                    if path[i + 1].starts_with("$") {
+                        if !node.get(key).is_some_and(Value::is_array) {
+                            node.insert(key.clone(), Value::Array(Vec::new()));
+                        }
+                    } else if !node.get(key).is_some_and(Value::is_object) {
                         node.insert(key.clone(), Value::Object(Map::new()));
                     }
                     json_obj = &mut node[key];
@@ -172,46 +715,114 @@ impl File {
         }
     }
 
-    pub fn validate(&self, schema: &Value) -> Result<(), ValidationError> {
+    /// Removes and returns the value at a path of keys, where `$<index>` segments address
+    /// array items.
+    pub fn remove(&mut self, path: &[String]) -> Option<Value> {
+        let (last, parents) = path.split_last()?;
+        let mut node = &mut self.data;
+        for key in parents {
+            node = match node {
+                Value::Object(obj) => obj.get_mut(key)?,
+                Value::Array(arr) => arr.get_mut(array_index(key)?)?,
+                _ => return None,
+            };
+        }
+        match node {
+            Value::Object(obj) => obj.shift_remove(last),
+            Value::Array(arr) => {
+                let i = array_index(last)?;
+                (i < arr.len()).then(|| arr.remove(i))
+            }
+            _ => None,
+        }
+    }
+
+    /// Orders object keys the way `schema` lists them in its `properties`, so that e.g.
+    /// `apiVersion` and `kind` always come first. Unlisted keys keep their order after them.
+    pub fn canonicalize(&mut self, schema: &Value) {
+        schema::canonical_order(&mut self.data, schema);
+    }
+
+    /// Adds the `default` values `schema` gives to missing properties, see
+    /// `schema::apply_defaults`
+    pub fn apply_defaults(&mut self, schema: &Value) {
+        schema::apply_defaults(&mut self.data, schema);
+    }
+    /// Validates the contents against a schema, failing with the first error. Kept as it was
+    /// before `validate_with`, which reports every error.
+    #[allow(clippy::result_large_err)]
+    pub fn validate(&self, schema: &Value) -> Result<(), ValidationError<'_>> {
         jsonschema::validate(schema, &self.data)
     }
+    /// Validates the contents against a compiled schema, reporting every error
+    pub fn validate_with(&self, validator: &Validator) -> ValidationReport {
+        ValidationReport::new(validator, std::slice::from_ref(self))
+    }
+    /// Merges an overlay into this file using the default merge strategy
     pub fn merge(&mut self, overlay: File) {
-        let stack_item = StackItem {
-            path: Vec::new(),
-            value: overlay.data,
-        };
-        let mut stack: Vec<StackItem> = Vec::new();
-        stack.push(stack_item);
-        let mut i = 0;
-
-        while i < stack.len() {
-            let stack_item = &stack[i];
-            let mut add_to_stack: Vec<StackItem> = Vec::new();
-            if let Value::Object(obj) = &stack_item.value {
-                for (key, value) in obj.into_iter() {
-                    let mut p = stack_item.path.clone();
-                    p.push(key.to_owned());
-
-                    add_to_stack.push(StackItem {
-                        path: p,
-                        value: value.clone(),
-                    });
-                }
-                stack.append(&mut add_to_stack);
-            } else if let Value::Array(arr) = &stack_item.value {
-                for (j, item) in arr.iter().enumerate() {
-                    let mut p = stack_item.path.clone();
-                    let mut k = "$".to_owned();
-                    let index = j.to_string();
-                    k.push_str(&index);
-                    p.push(k);
-                    self.insert(p, item.clone());
-                }
-                stack.append(&mut add_to_stack);
-            } else {
-                self.insert(stack[i].path.clone(), stack[i].value.clone());
-            }
-            i += 1;
-        }
+        self.merge_with(overlay, &MergeStrategy::default());
+    }
+    /// Merges an overlay into this file. Objects are merged recursively, arrays are combined
+    /// according to `strategy` and any other overlay value replaces the value in this file.
+    /// Directives such as `$patch: replace` or `$patch: delete` in the overlay are interpreted
+    /// as described in the `merge` module.
+    pub fn merge_with(&mut self, overlay: File, strategy: &MergeStrategy) {
+        let mut moves = merge::Moves::default();
+        merge::merge_values(
+            &mut self.data,
+            overlay.data,
+            strategy,
+            &mut Vec::new(),
+            &mut moves,
+        );
+        self.comments.merge(overlay.comments, &moves);
+        self.sources.merge(overlay.sources, &moves, &self.data);
+    }
+}
+
+/// Returns the 1-based line and column of a byte offset
+fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Returns the json pointer of the first `null` in a value
+fn null_pointer(value: &Value) -> Option<String> {
+    let escape = |key: &str| key.replace('~', "~0").replace('/', "~1");
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| null_pointer(item).map(|pointer| format!("/{}{}", i, pointer))),
+        Value::Object(entries) => entries.iter().find_map(|(key, item)| {
+            null_pointer(item).map(|pointer| format!("/{}{}", escape(key), pointer))
+        }),
+        _ => None,
+    }
+}
+
+/// Parses a `$<index>` path segment
+fn array_index(segment: &str) -> Option<usize> {
+    segment.strip_prefix('$')?.parse().ok()
+}
+
+fn read_to_string(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Writes a file, ending it with a newline
+fn write(path: &Path, mut contents: String) -> Result<(), Error> {
+    if !contents.ends_with('\n') {
+        contents.push('\n');
     }
+    fs::write(path, contents).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })
 }
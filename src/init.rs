@@ -1,739 +1,236 @@
+use crate::catalogue::{self, CatalogueTemplate};
+use crate::config::{self, Config, Project, CONFIG_FILE};
+use crate::error::Error;
+use crate::file;
+use crate::state;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
 
-pub fn init_directory(path: &str) {
-    let mut full_path = path.to_owned();
-    full_path.push_str("/gitcomet");
-    // create gitcomet folder
-    fs::create_dir(full_path.clone()).expect("oh no it wasnt possible to create a directory");
-    // create templates folder
-    full_path.push_str("/templates");
-    fs::create_dir(full_path.clone()).expect("oh no it wasnt possible to create a directory");
-    // create argocd folder
-    full_path.push_str("/argocd");
-    fs::create_dir(full_path.clone()).expect("oh no it wasnt possible to create a directory");
+/// What `init` does with one of the pieces of a project
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    /// Already there, with the content `init` would write, or the project config
+    Keep,
+    /// Already there with other content, which `init` didn't write or was edited since,
+    /// left alone unless forced
+    Conflict,
+    /// Overwritten because `init` wrote it and it is unchanged since, or because `init` was
+    /// forced
+    Overwrite,
+}
 
-    //create overlays folder
-    let mut overlays_folder = full_path.clone();
-    overlays_folder.push_str("/overlays");
-    fs::create_dir(overlays_folder.clone()).expect("oh no it wasnt possible to create a directory");
-    //create example helm_app.yaml
-    overlays_folder.push_str("/helm_app.yaml");
-    fs::write(overlays_folder, ARGOCD_OVERLAY_FILE).expect("couldn't write file");
+/// A folder, or a file and its content, `init` creates
+#[derive(Debug)]
+pub struct Piece {
+    pub path: PathBuf,
+    pub action: Action,
+    content: Option<String>,
+}
 
-    //create example schema file
-    let mut schema_file = full_path.clone();
-    schema_file.push_str("/schema.yaml");
-    fs::write(schema_file, ARGOCD_JSONSCHEMA).expect("couldn't write file");
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            Action::Create => "create",
+            Action::Keep => "keep",
+            Action::Conflict => "conflict",
+            Action::Overwrite => "overwrite",
+        };
+        write!(f, "{} {}", action, self.path.display())
+    }
+}
 
-    //create example base file
-    full_path.push_str("/base.yaml");
-    fs::write(full_path.clone(), ARGOCD_BASE_FILE).expect("couldn't write file");
+/// The pieces `init` creates in a project, worked out before anything is written so that it
+/// can be shown, e.g. for a dry run, and refused as a whole
+pub struct InitPlan {
+    pub project: Project,
+    pub pieces: Vec<Piece>,
 }
 
-const ARGOCD_BASE_FILE: &str = r#"apiVersion: argoproj.io/v1alpha1
-kind: Application
-metadata:
-  namespace: argocd
-  finalizers:
-    - resources-finalizer.argocd.argoproj.io
-spec:
-  project: default"#;
+impl InitPlan {
+    /// Pieces that would be written
+    pub fn changes(&self) -> impl Iterator<Item = &Piece> {
+        self.pieces
+            .iter()
+            .filter(|piece| matches!(piece.action, Action::Create | Action::Overwrite))
+    }
 
-const ARGOCD_OVERLAY_FILE: &str = r#"metadata:
-  name: guestbook
-  labels:
-    name: guestbook
-spec:
-  source:
-    repoURL: "https://github.com/argoproj/argocd-example-apps.git"
-    path: guestbook
-    helm: 
-      passCredentials: false # If true then adds --pass-credentials to Helm commands to pass credentials to all domains
-      # Extra parameters to set (same as setting through values.yaml, but these take precedence)
-      parameters:
-      - name: "nginx-ingress.controller.service.annotations.external-dns\\.alpha\\.kubernetes\\.io/hostname"
-        value: mydomain.example.com
-      - name: "ingress.annotations.kubernetes\\.io/tls-acme"
-        value: "true"
-        forceString: true # ensures that value is treated as a string
+    /// Pieces that are conflicts
+    pub fn conflicts(&self) -> impl Iterator<Item = &Piece> {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.action == Action::Conflict)
+    }
 
-      # Use the contents of files as parameters (uses Helm's --set-file)
-      fileParameters:
-      - name: config
-        path: files/config.json
+    /// Writes the pieces that are missing, or overwritten, and records what was written in the
+    /// state file. Fails without writing anything if there are conflicts.
+    pub fn apply(&self) -> Result<(), Error> {
+        let conflicts: Vec<PathBuf> = self.conflicts().map(|piece| piece.path.clone()).collect();
+        if !conflicts.is_empty() {
+            return Err(Error::Modified { paths: conflicts });
+        }
+        let mut written = Vec::new();
+        for piece in self.changes() {
+            match &piece.content {
+                Some(content) => {
+                    write(&piece.path, content)?;
+                    written.push((piece.path.as_path(), content.as_str()));
+                }
+                None => fs::create_dir_all(&piece.path).map_err(|source| file::Error::Io {
+                    path: piece.path.clone(),
+                    source,
+                })?,
+            }
+        }
+        state::record_written(&self.project, &written)
+    }
+}
 
-      # Release name override (defaults to application name)
-      releaseName: guestbook
+/// Catalogue template a new project gets when none are asked for
+pub const DEFAULT_TEMPLATE: &str = "argocd";
 
-      # Helm values files for overriding values in the helm chart
-      # The path is relative to the spec.source.path directory defined above
-      valueFiles:
-      - values-prod.yaml;
+/// Works out what `init` creates in `path`: its `overl.toml`, holding `config`, the templates
+/// folder and the given templates of the catalogue, `DEFAULT_TEMPLATE` for a new project if
+/// none are given. An existing project keeps its config, and only gets the pieces it is
+/// missing. Files `init` wrote are updated while unchanged since. Other files that differ from
+/// the catalogue, edited ones included, are conflicts, or overwritten with `force`.
+pub fn plan<P: AsRef<Path>>(
+    path: P,
+    config: Config,
+    templates: &[String],
+    force: bool,
+) -> Result<InitPlan, Error> {
+    let mut templates = templates
+        .iter()
+        .map(|name| catalogue_template(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let path = path.as_ref();
+    let mut pieces = Vec::new();
+    let project = if path.join(CONFIG_FILE).is_file() {
+        let project = Project::load(path)?;
+        pieces.push(Piece {
+            path: project.config_file(),
+            action: Action::Keep,
+            content: None,
+        });
+        project
+    } else {
+        let project = Project::new(path, config);
+        let content = project.config().to_toml_string();
+        pieces.push(file_piece(
+            project.config_file(),
+            content,
+            &BTreeMap::new(),
+            force,
+        ));
+        project
+    };
+    let written = state::written_hashes(&project)?;
+    let templates_folder = project.templates_dir();
+    if templates.is_empty() && pieces[0].action == Action::Create && !templates_folder.exists() {
+        templates.push(catalogue_template(DEFAULT_TEMPLATE)?);
+    }
+    pieces.push(Piece {
+        action: if templates_folder.is_dir() {
+            Action::Keep
+        } else {
+            Action::Create
+        },
+        path: templates_folder,
+        content: None,
+    });
+    for template in templates {
+        for (path, content) in template_files(&project, template, template.name) {
+            pieces.push(file_piece(path, content.to_owned(), &written, force));
+        }
+    }
+    Ok(InitPlan { project, pieces })
+}
 
-      # Ignore locally missing valueFiles when installing Helm chart. Defaults to false
-      ignoreMissingValueFiles: false
+/// Compares a file `init` writes with what is on disk and, through the hashes of `written`,
+/// with what `init` wrote there before
+fn file_piece(
+    path: PathBuf,
+    content: String,
+    written: &BTreeMap<PathBuf, String>,
+    force: bool,
+) -> Piece {
+    let action = match (fs::read_to_string(&path), written.get(&path)) {
+        (Err(_), _) if !path.exists() => Action::Create,
+        (Ok(existing), _) if existing == content => Action::Keep,
+        (Ok(existing), Some(hash)) if state::text_hash(&existing) == *hash => Action::Overwrite,
+        _ if force => Action::Overwrite,
+        _ => Action::Conflict,
+    };
+    Piece {
+        path,
+        action,
+        content: Some(content),
+    }
+}
 
-      # Values file as block file. Prefer to use valuesObject if possible (see below)
-      values: |
-        ingress:
-          enabled: true
-          path: /
-          hosts:
-            - mydomain.example.com
-          annotations:
-            kubernetes.io/ingress.class: nginx
-            kubernetes.io/tls-acme: "true"
-          labels: {}
-          tls:
-            - secretName: mydomain-tls
-              hosts:
-                - mydomain.example.com
+/// Looks a template up in the catalogue, failing if it isn't there
+pub fn catalogue_template(name: &str) -> Result<&'static CatalogueTemplate, Error> {
+    catalogue::find(name).ok_or_else(|| Error::UnknownCatalogueTemplate {
+        name: name.to_owned(),
+    })
+}
 
-      # Values file as block file. This takes precedence over values
-      valuesObject:
-        ingress:
-          enabled: true
-          path: /
-          hosts:
-            - mydomain.example.com
-          annotations:
-            kubernetes.io/ingress.class: nginx
-            kubernetes.io/tls-acme: "true"
-          labels: {}
-          tls:
-            - secretName: mydomain-tls
-              hosts:
-                - mydomain.example.com"#;
+/// Adds a template of the catalogue to a project under `name` and returns its folder. An
+/// existing template is never overwritten.
+pub fn add_template(
+    project: &Project,
+    template: &CatalogueTemplate,
+    name: &str,
+) -> Result<PathBuf, Error> {
+    let template_folder = new_template_folder(project, name)?;
+    for (path, content) in template_files(project, template, name) {
+        write(&path, content)?;
+    }
+    Ok(template_folder)
+}
+
+/// Returns the folder of a template that doesn't exist yet, failing if it does or if `name`
+/// isn't a plain file name
+pub(crate) fn new_template_folder(project: &Project, name: &str) -> Result<PathBuf, Error> {
+    config::check_name(name)?;
+    let template_folder = project.template_dir(name);
+    if template_folder.exists() {
+        return Err(Error::AlreadyExists {
+            path: template_folder,
+        });
+    }
+    Ok(template_folder)
+}
 
-const ARGOCD_JSONSCHEMA: &str = r##""$schema": http://json-schema.org/draft-06/schema#
-"$ref": "#/definitions/ArgoCDApplication"
-definitions:
-  ArgoCDApplication:
-    type: object
-    additionalProperties: false
-    properties:
-      apiVersion:
-        const: argoproj.io/v1alpha1
-      kind:
-        const: Application
-      metadata:
-        "$ref": "#/definitions/Metadata"
-      spec:
-        "$ref": "#/definitions/Spec"
-    required:
-    - apiVersion
-    - kind
-    - metadata
-    - spec
-    title: ArgoCDApplication
-  Metadata:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      namespace:
-        type: string
-        default: default
-      finalizers:
-        type: array
-        items:
-          type: string
-      labels:
-        "$ref": "#/definitions/MetadataLabels"
-      annotations:
-        "$ref": "#/definitions/MetadataLabels"
-    required:
-    - name
-    title: Metadata
-  MetadataLabels:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-    required:
-    - name
-    title: MetadataLabels
-  Spec:
-    type: object
-    additionalProperties: false
-    properties:
-      project:
-        type: string
-      source:
-        "$ref": "#/definitions/PurpleSource"
-      sources:
-        type: array
-        items:
-          "$ref": "#/definitions/SourceElement"
-      destination:
-        "$ref": "#/definitions/Destination"
-      info:
-        type: array
-        items:
-          "$ref": "#/definitions/Info"
-      syncPolicy:
-        "$ref": "#/definitions/SyncPolicy"
-      ignoreDifferences:
-        type: array
-        items:
-          "$ref": "#/definitions/IgnoreDifference"
-      revisionHistoryLimit:
-        type: integer
-    required:
-    - destination
-    - project
-    title: Spec
-  Destination:
-    type: object
-    additionalProperties: false
-    properties:
-      server:
-        type: string
-        format: uri
-        qt-uri-protocols:
-        - https
-      namespace:
-        type: string
-    required:
-    - namespace
-    - server
-    title: Destination
-  IgnoreDifference:
-    type: object
-    additionalProperties: false
-    properties:
-      group:
-        type: string
-      kind:
-        type: string
-      jsonPointers:
-        type: array
-        items:
-          type: string
-      jqPathExpressions:
-        type: array
-        items:
-          type: string
-      managedFieldsManagers:
-        type: array
-        items:
-          type: string
-      name:
-        type: string
-      namespace:
-        type: string
-    required:
-    - kind
-    title: IgnoreDifference
-  Info:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      value:
-        type: string
-        qt-uri-protocols:
-        - https
-    required:
-    - name
-    - value
-    title: Info
-  PurpleSource:
-    type: object
-    additionalProperties: false
-    properties:
-      repoURL:
-        type: string
-        format: uri
-        qt-uri-protocols:
-        - https
-        qt-uri-extensions:
-        - ".git"
-      targetRevision:
-        type: string
-      path:
-        type: string
-      chart:
-        type: string
-      helm:
-        "$ref": "#/definitions/Helm"
-      kustomize:
-        "$ref": "#/definitions/Kustomize"
-      directory:
-        "$ref": "#/definitions/Directory"
-      plugin:
-        "$ref": "#/definitions/Plugin"
-    required:
-    - repoURL
-    - targetRevision
-    title: PurpleSource
-  Directory:
-    type: object
-    additionalProperties: false
-    properties:
-      recurse:
-        type: boolean
-      jsonnet:
-        "$ref": "#/definitions/Jsonnet"
-      exclude:
-        type: string
-      include:
-        type: string
-    required:
-    - exclude
-    - include
-    - jsonnet
-    - recurse
-    title: Directory
-  Jsonnet:
-    type: object
-    additionalProperties: false
-    properties:
-      extVars:
-        type: array
-        items:
-          "$ref": "#/definitions/EXTVar"
-      tlas:
-        type: array
-        items:
-          "$ref": "#/definitions/EXTVar"
-    required:
-    - extVars
-    - tlas
-    title: Jsonnet
-  EXTVar:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      value:
-        type: string
-      code:
-        type: boolean
-    required:
-    - name
-    - value
-    title: EXTVar
-  Helm:
-    type: object
-    additionalProperties: false
-    properties:
-      passCredentials:
-        type: boolean
-      parameters:
-        type: array
-        items:
-          "$ref": "#/definitions/HelmParameter"
-      fileParameters:
-        type: array
-        items:
-          "$ref": "#/definitions/FileParameter"
-      releaseName:
-        type: string
-      valueFiles:
-        type: array
-        items:
-          type: string
-      ignoreMissingValueFiles:
-        type: boolean
-      values:
-        type: string
-      valuesObject:
-        "$ref": "#/definitions/ValuesObject"
-      skipCrds:
-        type: boolean
-      version:
-        type: string
-      kubeVersion:
-        type: string
-      apiVersions:
-        type: array
-        items:
-          type: string
-      namespace:
-        type: string
-    required:
-    - namespace
-    - version
-    title: Helm
-  FileParameter:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      path:
-        type: string
-    required:
-    - name
-    - path
-    title: FileParameter
-  HelmParameter:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      value:
-        type: string
-      forceString:
-        type: boolean
-    required:
-    - name
-    - value
-    title: HelmParameter
-  ValuesObject:
-    type: object
-    additionalProperties: false
-    properties:
-      ingress:
-        "$ref": "#/definitions/Ingress"
-    required:
-    - ingress
-    title: ValuesObject
-  Ingress:
-    type: object
-    additionalProperties: false
-    properties:
-      enabled:
-        type: boolean
-      path:
-        type: string
-      hosts:
-        type: array
-        items:
-          type: string
-      annotations:
-        "$ref": "#/definitions/IngressAnnotations"
-      labels:
-        "$ref": "#/definitions/IngressLabels"
-      tls:
-        type: array
-        items:
-          "$ref": "#/definitions/Tl"
-    required:
-    - annotations
-    - enabled
-    - hosts
-    - labels
-    - path
-    - tls
-    title: Ingress
-  IngressAnnotations:
-    type: object
-    additionalProperties: false
-    properties:
-      kubernetes.io/ingress.class:
-        type: string
-      kubernetes.io/tls-acme:
-        type: string
-        format: boolean
-    required:
-    - kubernetes.io/ingress.class
-    - kubernetes.io/tls-acme
-    title: IngressAnnotations
-  IngressLabels:
-    type: object
-    additionalProperties: false
-    title: IngressLabels
-  Tl:
-    type: object
-    additionalProperties: false
-    properties:
-      secretName:
-        type: string
-      hosts:
-        type: array
-        items:
-          type: string
-    required:
-    - hosts
-    - secretName
-    title: Tl
-  Kustomize:
-    type: object
-    additionalProperties: false
-    properties:
-      version:
-        type: string
-      namePrefix:
-        type: string
-      nameSuffix:
-        type: string
-      commonLabels:
-        "$ref": "#/definitions/CommonLabels"
-      commonAnnotations:
-        "$ref": "#/definitions/CommonAnnotations"
-      commonAnnotationsEnvsubst:
-        type: boolean
-      forceCommonLabels:
-        type: boolean
-      forceCommonAnnotations:
-        type: boolean
-      images:
-        type: array
-        items:
-          type: string
-      namespace:
-        type: string
-      replicas:
-        type: array
-        items:
-          "$ref": "#/definitions/Replica"
-      components:
-        type: array
-        items:
-          type: string
-      patches:
-        type: array
-        items:
-          "$ref": "#/definitions/Patch"
-      kubeVersion:
-        type: string
-      apiVersions:
-        type: array
-        items:
-          type: string
-    required:
-    - apiVersions
-    - commonAnnotations
-    - commonAnnotationsEnvsubst
-    - commonLabels
-    - components
-    - forceCommonAnnotations
-    - forceCommonLabels
-    - images
-    - kubeVersion
-    - namePrefix
-    - nameSuffix
-    - namespace
-    - patches
-    - replicas
-    - version
-    title: Kustomize
-  CommonAnnotations:
-    type: object
-    additionalProperties: false
-    properties:
-      beep:
-        type: string
-    required:
-    - beep
-    title: CommonAnnotations
-  CommonLabels:
-    type: object
-    additionalProperties: false
-    properties:
-      foo:
-        type: string
-    required:
-    - foo
-    title: CommonLabels
-  Patch:
-    type: object
-    additionalProperties: false
-    properties:
-      target:
-        "$ref": "#/definitions/Target"
-      patch:
-        type: string
-    required:
-    - patch
-    - target
-    title: Patch
-  Target:
-    type: object
-    additionalProperties: false
-    properties:
-      kind:
-        type: string
-      name:
-        type: string
-    required:
-    - kind
-    - name
-    title: Target
-  Replica:
-    type: objec
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      count:
-        type: integer
-    required:
-    - count
-    - name
-    title: Replica
-  Plugin:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      env:
-        type: array
-        items:
-          "$ref": "#/definitions/Info"
-      parameters:
-        type: array
-        items:
-          "$ref": "#/definitions/PluginParameter"
-    required:
-    - env
-    - name
-    - parameters
-    title: Plugin
-  PluginParameter:
-    type: object
-    additionalProperties: false
-    properties:
-      name:
-        type: string
-      string:
-        type: string
-      array:
-        type: array
-        items:
-          type: string
-      map:
-        "$ref": "#/definitions/Map"
-    required:
-    - name
-    title: PluginParameter
-  Map:
-    type: object
-    additionalProperties: false
-    properties:
-      param-name:
-        type: string
-    required:
-    - param-name
-    title: Map
-  SourceElement:
-    type: object
-    additionalProperties: false
-    properties:
-      repoURL:
-        type: string
-        format: uri
-        qt-uri-protocols:
-        - https
-        qt-uri-extensions:
-        - ".git"
-      targetRevision:
-        type: string
-      path:
-        type: string
-      ref:
-        type: string
-    required:
-    - path
-    - ref
-    - repoURL
-    - targetRevision
-    title: SourceElement
-  SyncPolicy:
-    type: object
-    additionalProperties: false
-    properties:
-      automated:
-        "$ref": "#/definitions/Automated"
-      syncOptions:
-        type: array
-        items:
-          type: string
-      managedNamespaceMetadata:
-        "$ref": "#/definitions/ManagedNamespaceMetadata"
-      retry:
-        "$ref": "#/definitions/Retry"
-    required:
-    - automated
-    - managedNamespaceMetadata
-    - retry
-    - syncOptions
-    title: SyncPolicy
-  Automated:
-    type: object
-    additionalProperties: false
-    properties:
-      prune:
-        type: boolean
-      selfHeal:
-        type: boolean
-      allowEmpty:
-        type: boolean
-    required:
-    - allowEmpty
-    - prune
-    - selfHeal
-    title: Automated
-  ManagedNamespaceMetadata:
-    type: object
-    additionalProperties: false
-    properties:
-      labels:
-        "$ref": "#/definitions/ManagedNamespaceMetadataLabels"
-      annotations:
-        "$ref": "#/definitions/ManagedNamespaceMetadataAnnotations"
-    required:
-    - annotations
-    - labels
-    title: ManagedNamespaceMetadata
-  ManagedNamespaceMetadataAnnotations:
-    type: object
-    additionalProperties: false
-    properties:
-      the:
-        type: string
-      applies:
-        type: string
-      annotations:
-        type: string
-    required:
-    - annotations
-    - applies
-    - the
-    title: ManagedNamespaceMetadataAnnotations
-  ManagedNamespaceMetadataLabels:
-    type: object
-    additionalProperties: false
-    properties:
-      any:
-        type: string
-      you:
-        type: string
-    required:
-    - any
-    - you
-    title: ManagedNamespaceMetadataLabels
-  Retry:
-    type: object
-    additionalProperties: false
-    properties:
-      limit:
-        type: integer
-      backoff:
-        "$ref": "#/definitions/Backoff"
-    required:
-    - backoff
-    - limit
-    title: Retry
-  Backoff:
-    type: object
-    additionalProperties: false
-    properties:
-      duration:
-        type: string
-      factor:
-        type: integer
-      maxDuration:
-        type: string
-    required:
-    - duration
-    - factor
-    - maxDuration"##;
+/// Lists the files of a catalogue template added to a project under `name`
+fn template_files(
+    project: &Project,
+    template: &CatalogueTemplate,
+    name: &str,
+) -> Vec<(PathBuf, &'static str)> {
+    let template_folder = project.template_dir(name);
+    let mut files = vec![
+        (template_folder.join("base.yaml"), template.base),
+        (template_folder.join("schema.yaml"), template.schema),
+    ];
+    let overlays_folder = project.overlays_dir(name);
+    for (file_name, overlay) in template.overlays {
+        files.push((overlays_folder.join(file_name), *overlay));
+    }
+    files
+}
+
+/// Writes a file, creating the folders it is in
+pub(crate) fn write(path: &Path, content: &str) -> Result<(), file::Error> {
+    let io_error = |source| file::Error::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).map_err(io_error)?;
+    }
+    fs::write(path, content).map_err(io_error)
+}
@@ -1,3 +1,16 @@
+pub mod catalogue;
+pub mod comments;
+pub mod config;
+pub mod error;
+pub mod extract;
 pub mod file;
 pub mod init;
+pub mod merge;
+pub mod render;
+pub mod report;
+pub mod scaffold;
+pub mod schema;
+pub mod sources;
 pub mod state;
+pub mod validate;
+pub mod yaml;
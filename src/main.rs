@@ -1,4 +1,18 @@
-use overl::{init, state};
+use overl::config::{Config, Project};
+use overl::error::Error;
+use overl::file::{File, LoadOptions};
+use overl::init::Action;
+use overl::merge::{ArrayMerge, MergeStrategy};
+use overl::report::{ReportFormat, ValidationReport};
+use overl::state::RenderedImplementation;
+use overl::validate::TemplateCheck;
+use overl::{catalogue, extract, file, init, render, scaffold, schema, state, validate};
+use serde_json::Value;
+use similar::TextDiff;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
 use clap::{Parser, Subcommand};
 
@@ -12,32 +26,557 @@ struct Args {
 
 #[derive(Subcommand, Debug, Clone)]
 enum Commands {
-    Init { folder_path: Option<String> },
+    /// Creates a project, its `overl.toml` and templates from the built-in catalogue
+    Init {
+        folder_path: Option<String>,
+        /// Project name, the name of the folder by default
+        #[arg(long)]
+        name: Option<String>,
+        /// Catalogue templates to add, argocd by default for a new project
+        #[arg(short, long = "template")]
+        templates: Vec<String>,
+        /// List the templates of the catalogue instead
+        #[arg(long)]
+        list_templates: bool,
+        /// Overwrite files that are in the way, edited ones included
+        #[arg(long)]
+        force: bool,
+        /// Show what would be created without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Records the templates of the project in its state file
     Sync,
+    /// Merges overlays onto the base of a template and validates the result against its schema
+    Render {
+        template: String,
+        /// Overlays to apply in order, either by name or as a path to a file, e.g. ./prod.yaml
+        overlays: Vec<String>,
+        /// Write the rendered output to this file instead of stdout, in the format of its extension
+        #[arg(short, long)]
+        output: Option<String>,
+        #[command(flatten)]
+        merge: MergeArgs,
+        /// Fill in missing properties with the defaults of the schema before validating
+        #[arg(long)]
+        defaults: bool,
+        /// Order keys as the schema lists them instead of keeping the order of the base
+        #[arg(long)]
+        canonical: bool,
+        /// Keep the comments of the base and overlays in the rendered yaml
+        #[arg(long)]
+        comments: bool,
+        /// Read yaml without losing data: non-string keys, anchors, custom tags and special floats
+        #[arg(long)]
+        lossless: bool,
+        /// How schema errors are reported: text or json
+        #[arg(long = "report-format", default_value_t = ReportFormat::Text)]
+        report_format: ReportFormat,
+    },
+    /// Renders every template with each of its overlays and validates the results against the
+    /// template schemas
+    Validate {
+        #[command(flatten)]
+        merge: MergeArgs,
+        /// Fill in missing properties with the defaults of the schema before validating
+        #[arg(long)]
+        defaults: bool,
+        /// Read yaml without losing data: non-string keys, anchors, custom tags and special floats
+        #[arg(long)]
+        lossless: bool,
+        /// How results are reported: text or json
+        #[arg(long = "report-format", default_value_t = ReportFormat::Text)]
+        report_format: ReportFormat,
+    },
+    /// Works with template schemas
+    Schema {
+        #[command(subcommand)]
+        cmd: SchemaCommands,
+    },
+    /// Manages the templates of the project
+    Template {
+        #[command(subcommand)]
+        cmd: TemplateCommands,
+    },
+    /// Works with template overlays
+    Overlay {
+        #[command(subcommand)]
+        cmd: OverlayCommands,
+    },
+    /// Manages implementations, named uses of a template version with their own overlays
+    Impl {
+        #[command(subcommand)]
+        cmd: ImplCommands,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum SchemaCommands {
+    /// Drafts a schema from the base of a template rendered with each of its overlays
+    Infer {
+        template: String,
+        /// Write the schema to this file instead of stdout, in the format of its extension
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum TemplateCommands {
+    /// Adds a template of the built-in catalogue to the project
+    Add {
+        template: String,
+        /// Name of the template in the project, the catalogue name by default
+        #[arg(long = "as")]
+        name: Option<String>,
+    },
+    /// Creates a template from existing files, converted to the format of the project
+    Import {
+        name: String,
+        /// File to use as the base
+        #[arg(long)]
+        base: PathBuf,
+        /// Schema of the template, inferred from the base and overlays by default
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Files to use as overlays
+        #[arg(long = "overlay")]
+        overlays: Vec<PathBuf>,
+    },
+    /// Creates a template from similar files, their common part as the base and what sets each
+    /// one apart as an overlay
+    Extract {
+        name: String,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum OverlayCommands {
+    /// Creates an overlay setting every property the schema requires and the base doesn't set
+    New { template: String, name: String },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ImplCommands {
+    /// Adds a named implementation of a synced template
+    Add {
+        name: String,
+        template: String,
+        /// Overlays of the template to apply in order
+        overlays: Vec<String>,
+        /// Template version to pin, the current one by default
+        #[arg(long)]
+        version: Option<u32>,
+        /// File holding the implementation's own overlay, applied after the others
+        #[arg(long)]
+        values: Option<String>,
+    },
+    /// Lists the implementations of every template
+    List,
+    /// Removes an implementation
+    Remove { name: String },
+    /// Renders an implementation with the template version it is pinned to
+    Render {
+        name: String,
+        /// Write the rendered output to this file instead of stdout, in the format of its extension
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Write the rendered output to `<name>.yaml` in the output folder of the project
+        #[arg(long, conflicts_with = "output")]
+        write: bool,
+        #[command(flatten)]
+        merge: MergeArgs,
+    },
+    /// Pins an implementation to another template version after validating it and showing the
+    /// changes
+    Upgrade {
+        name: String,
+        /// Version to upgrade to, the current one by default
+        #[arg(long)]
+        to: Option<u32>,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        #[command(flatten)]
+        merge: MergeArgs,
+    },
+}
+
+/// How overlays are merged into the base
+#[derive(clap::Args, Debug, Clone)]
+struct MergeArgs {
+    /// How overlay arrays are merged: replace, append, prepend, index or key:<field>
+    #[arg(long, default_value_t = ArrayMerge::Append)]
+    arrays: ArrayMerge,
+    /// Array merge strategy for a single array, e.g. /spec/source/helm/parameters=key:name
+    #[arg(long = "array-path", value_parser = parse_array_path)]
+    array_paths: Vec<(String, ArrayMerge)>,
+    /// Treat null overlay values as deleting the key they are set on
+    #[arg(long)]
+    null_deletes: bool,
+}
+
+impl MergeArgs {
+    fn strategy(self) -> MergeStrategy {
+        let mut strategy = MergeStrategy::new(self.arrays).with_null_deletes(self.null_deletes);
+        for (pointer, arrays) in self.array_paths {
+            strategy = strategy.with_path(&pointer, arrays);
+        }
+        strategy
+    }
 }
 
 fn main() {
     let args = Args::parse();
     match args.cmd {
-        Commands::Init { folder_path } => {
-            if let Some(path) = folder_path {
-                init::init_directory(&path);
-            } else {
-                init::init_directory(".");
+        Commands::Init {
+            folder_path,
+            name,
+            templates,
+            list_templates,
+            force,
+            dry_run,
+        } => {
+            if list_templates {
+                for template in &catalogue::CATALOGUE {
+                    println!("{}\t{}", template.name, template.description);
+                }
+                return;
+            }
+            let path = folder_path.unwrap_or_else(|| ".".to_owned());
+            let mut config = Config::default();
+            if let Some(name) = name.or_else(|| folder_name(&path)) {
+                config.name = name;
+            }
+            let plan = init::plan(&path, config, &templates, force)
+                .unwrap_or_else(|error| exit_with_error(error));
+            if dry_run {
+                for piece in &plan.pieces {
+                    println!("{}", piece);
+                }
+                if plan.conflicts().next().is_some() {
+                    process::exit(1);
+                }
+                return;
+            }
+            if let Err(error) = plan.apply() {
+                exit_with_error(error);
+            }
+            if plan.changes().next().is_none() {
+                println!("Nothing to create in {}", plan.project.root().display());
+            }
+            for piece in plan.changes() {
+                match piece.action {
+                    Action::Overwrite => println!("Overwrote {}", piece.path.display()),
+                    _ => println!("Created {}", piece.path.display()),
+                }
             }
         }
         Commands::Sync => {
-            state::sync_state_file("gitcomet", "./gitcomet/");
+            let warnings =
+                state::sync_state_file(&project()).unwrap_or_else(|error| exit_with_error(error));
+            for (template, warning) in warnings {
+                eprintln!("Warning: {}: {}", template, warning);
+            }
         }
+        Commands::Render {
+            template,
+            overlays,
+            output,
+            merge,
+            defaults,
+            canonical,
+            comments,
+            lossless,
+            report_format,
+        } => {
+            let project = project();
+            let strategy = merge.strategy();
+            let options = LoadOptions { comments, lossless };
+            let mut rendered =
+                render::render_template(&project, &template, &overlays, &strategy, &options)
+                    .unwrap_or_else(|error| exit_with_error(error));
+            let (schema, validator) = render::template_validator(&project, &template)
+                .unwrap_or_else(|error| exit_with_error(error));
+            for document in rendered.iter_mut() {
+                if defaults {
+                    document.apply_defaults(schema.data());
+                }
+                if canonical {
+                    document.canonicalize(schema.data());
+                }
+            }
+            let report = ValidationReport::new(&validator, &rendered);
+            if !report.is_valid() {
+                match report_format {
+                    ReportFormat::Text => eprint!("{}", report),
+                    ReportFormat::Json => println!("{}", report.to_json()),
+                }
+                process::exit(1);
+            }
+            if let Some(path) = output {
+                if let Err(error) = file::save_documents(&rendered, &path) {
+                    exit_with_error(error);
+                }
+            } else {
+                println!("{}", file::documents_to_yaml_string(&rendered));
+            }
+        }
+        Commands::Validate {
+            merge,
+            defaults,
+            lossless,
+            report_format,
+        } => {
+            let options = LoadOptions {
+                comments: false,
+                lossless,
+            };
+            let checks =
+                validate::check_templates(&project(), &merge.strategy(), &options, defaults)
+                    .unwrap_or_else(|error| exit_with_error(error));
+            match report_format {
+                ReportFormat::Text => {
+                    for check in &checks {
+                        print!("{}", check);
+                    }
+                }
+                ReportFormat::Json => {
+                    let checks: Vec<_> = checks.iter().map(TemplateCheck::to_json).collect();
+                    println!("{}", serde_json::to_string_pretty(&checks).unwrap());
+                }
+            }
+            if !checks.iter().all(TemplateCheck::is_valid) {
+                process::exit(1);
+            }
+        }
+        Commands::Schema {
+            cmd: SchemaCommands::Infer { template, output },
+        } => {
+            let project = project();
+            let combinations = render::overlay_combinations(&project, &template)
+                .unwrap_or_else(|error| exit_with_error(error));
+            let mut documents = Vec::new();
+            for overlay in combinations {
+                let rendered = render::render_template(
+                    &project,
+                    &template,
+                    overlay.as_slice(),
+                    &MergeStrategy::default(),
+                    &LoadOptions::default(),
+                )
+                .unwrap_or_else(|error| exit_with_error(error));
+                documents.extend(rendered);
+            }
+            let samples: Vec<&Value> = documents.iter().map(File::data).collect();
+            let inferred = File::new(schema::infer(&samples));
+            if let Some(path) = output {
+                if let Err(error) = inferred.save(&path) {
+                    exit_with_error(error);
+                }
+            } else {
+                println!("{}", inferred.to_yaml_string());
+            }
+        }
+        Commands::Template { cmd } => {
+            let created = match cmd {
+                TemplateCommands::Add { template, name } => {
+                    let template = init::catalogue_template(&template)
+                        .unwrap_or_else(|error| exit_with_error(error));
+                    let name = name.as_deref().unwrap_or(template.name);
+                    init::add_template(&project(), template, name)
+                }
+                TemplateCommands::Import {
+                    name,
+                    base,
+                    schema,
+                    overlays,
+                } => scaffold::import_template(
+                    &project(),
+                    &name,
+                    &base,
+                    schema.as_deref(),
+                    &overlays,
+                ),
+                TemplateCommands::Extract { name, files } => {
+                    extract::extract_template(&project(), &name, &files)
+                }
+            };
+            match created {
+                Ok(path) => println!("Created {}", path.display()),
+                Err(error) => exit_with_error(error),
+            }
+        }
+        Commands::Overlay {
+            cmd: OverlayCommands::New { template, name },
+        } => match scaffold::new_overlay(&project(), &template, &name) {
+            Ok(path) => println!("Created {}", path.display()),
+            Err(error) => exit_with_error(error),
+        },
+        Commands::Impl { cmd } => match cmd {
+            ImplCommands::Add {
+                name,
+                template,
+                overlays,
+                version,
+                values,
+            } => {
+                let overlay = match values {
+                    Some(path) => File::load(path)
+                        .unwrap_or_else(|error| exit_with_error(error))
+                        .into_data(),
+                    None => Value::Null,
+                };
+                if let Err(error) = state::add_implementation(
+                    &project(),
+                    &template,
+                    &name,
+                    version,
+                    &overlays,
+                    overlay,
+                ) {
+                    exit_with_error(error);
+                }
+            }
+            ImplCommands::List => {
+                let implementations = state::list_implementations(&project())
+                    .unwrap_or_else(|error| exit_with_error(error));
+                for (template, implementation) in implementations {
+                    println!(
+                        "{}\t{} v{}\t{}",
+                        implementation.name,
+                        template,
+                        implementation.version,
+                        implementation.overlays.join(", ")
+                    );
+                }
+            }
+            ImplCommands::Remove { name } => {
+                if let Err(error) = state::remove_implementation(&project(), &name) {
+                    exit_with_error(error);
+                }
+            }
+            ImplCommands::Render {
+                name,
+                output,
+                write,
+                merge,
+            } => {
+                let project = project();
+                let rendered =
+                    state::render_implementation(&project, &name, None, &merge.strategy())
+                        .unwrap_or_else(|error| exit_with_error(error));
+                validate_implementation(&rendered);
+                let output = match write {
+                    true => {
+                        let folder = project.output_dir();
+                        if let Err(source) = fs::create_dir_all(&folder) {
+                            exit_with_error(file::Error::Io {
+                                path: folder,
+                                source,
+                            });
+                        }
+                        Some(folder.join(format!("{}.yaml", name)))
+                    }
+                    false => output.map(PathBuf::from),
+                };
+                if let Some(path) = output {
+                    if let Err(error) = file::save_documents(&rendered.documents, &path) {
+                        exit_with_error(error);
+                    }
+                } else {
+                    println!("{}", file::documents_to_yaml_string(&rendered.documents));
+                }
+            }
+            ImplCommands::Upgrade {
+                name,
+                to,
+                yes,
+                merge,
+            } => {
+                let project = project();
+                let strategy = merge.strategy();
+                let version = match to {
+                    Some(version) => version,
+                    None => state::current_version(&project, &name)
+                        .unwrap_or_else(|error| exit_with_error(error)),
+                };
+                let pinned = state::render_implementation(&project, &name, None, &strategy)
+                    .unwrap_or_else(|error| exit_with_error(error));
+                if pinned.version == version {
+                    println!("{} is already on version {}", name, version);
+                    return;
+                }
+                let upgraded =
+                    state::render_implementation(&project, &name, Some(version), &strategy)
+                        .unwrap_or_else(|error| exit_with_error(error));
+                validate_implementation(&upgraded);
+                let old = file::documents_to_yaml_string(&pinned.documents) + "\n";
+                let new = file::documents_to_yaml_string(&upgraded.documents) + "\n";
+                print!(
+                    "{}",
+                    TextDiff::from_lines(&old, &new).unified_diff().header(
+                        &format!("{} v{}", name, pinned.version),
+                        &format!("{} v{}", name, upgraded.version),
+                    )
+                );
+                if !yes && !confirm(&format!("Upgrade {} to version {}?", name, version)) {
+                    return;
+                }
+                if let Err(error) = state::upgrade_implementation(&project, &name, version) {
+                    exit_with_error(error);
+                }
+                println!("Upgraded {} to version {}", name, version);
+            }
+        },
+    }
+}
+
+/// Validates an implementation against the schema of the template version it was rendered with,
+/// exiting with the errors if it is invalid
+fn validate_implementation(rendered: &RenderedImplementation) {
+    let schema_name = format!("{} version {} schema", rendered.template, rendered.version);
+    let validator = schema::validator(&File::new(rendered.schema.clone()), Path::new(&schema_name))
+        .unwrap_or_else(|error| exit_with_error(error));
+    let report = ValidationReport::new(&validator, &rendered.documents);
+    if !report.is_valid() {
+        eprint!("{}", report);
+        process::exit(1);
+    }
+}
+
+/// Finds the project the current folder belongs to, exiting if its config is unreadable
+fn project() -> Project {
+    Project::discover(".").unwrap_or_else(|error| exit_with_error(error))
+}
+
+/// Returns the name of a folder, resolving `.` and `..`
+fn folder_name(path: &str) -> Option<String> {
+    let path = Path::new(path).canonicalize().ok()?;
+    Some(path.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Asks a yes or no question on stdin, anything but `y` or `yes` meaning no
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
     }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+fn exit_with_error(error: impl Into<Error>) -> ! {
+    eprintln!("Error: {}", error.into());
+    process::exit(1);
+}
 
-    // let mut base = File::from_yaml("./example/templates/argocd/base.yaml");
-    // let overlay = File::from_yaml("./example/templates/argocd/testrequest.yaml");
-    // let schema = File::from_yaml("./example/templates/argocd/schema.yaml");
-    // base.merge(overlay);
-    // let validator = jsonschema::validator_for(&schema.data()).expect("hey");
-    // for error in validator.iter_errors(base.data()) {
-    //     eprintln!("Error: {}", error);
-    //     eprintln!("Location: {}", error.instance_path);
-    // }
+fn parse_array_path(s: &str) -> Result<(String, ArrayMerge), String> {
+    let (pointer, arrays) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <pointer>=<strategy>, got {}", s))?;
+    Ok((pointer.to_owned(), arrays.parse()?))
 }
@@ -0,0 +1,542 @@
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// How an array in an overlay is combined with the array at the same location in the base
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The overlay array replaces the base array
+    Replace,
+    /// Overlay items are added after the base items
+    #[default]
+    Append,
+    /// Overlay items are added before the base items
+    Prepend,
+    /// Items at the same index are merged, extra overlay items are appended
+    ByIndex,
+    /// Objects sharing the same value for the given field are merged, other items are appended.
+    /// This is how kubernetes style lists of named objects, e.g. helm parameters, are patched.
+    ByKey(String),
+}
+
+impl FromStr for ArrayMerge {
+    type Err = String;
+
+    /// Parses `replace`, `append`, `prepend`, `index` or `key:<field>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "replace" => Ok(ArrayMerge::Replace),
+            "append" => Ok(ArrayMerge::Append),
+            "prepend" => Ok(ArrayMerge::Prepend),
+            "index" => Ok(ArrayMerge::ByIndex),
+            _ => match s.strip_prefix("key:") {
+                Some(field) if !field.is_empty() => Ok(ArrayMerge::ByKey(field.to_owned())),
+                _ => Err(format!(
+                    "unknown array merge strategy {}, expected replace, append, prepend, index or key:<field>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ArrayMerge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrayMerge::Replace => write!(f, "replace"),
+            ArrayMerge::Append => write!(f, "append"),
+            ArrayMerge::Prepend => write!(f, "prepend"),
+            ArrayMerge::ByIndex => write!(f, "index"),
+            ArrayMerge::ByKey(field) => write!(f, "key:{}", field),
+        }
+    }
+}
+
+/// Selects the array merge strategy used when merging an overlay, with optional overrides for
+/// specific arrays.
+#[derive(Clone, Debug, Default)]
+pub struct MergeStrategy {
+    arrays: ArrayMerge,
+    paths: Vec<(Vec<String>, ArrayMerge)>,
+    null_deletes: bool,
+}
+
+impl MergeStrategy {
+    pub fn new(arrays: ArrayMerge) -> Self {
+        MergeStrategy {
+            arrays,
+            paths: Vec::new(),
+            null_deletes: false,
+        }
+    }
+
+    /// Makes `null` overlay values delete the key they are set on, like a json merge patch,
+    /// instead of setting it to `null`. With the `index` strategy, `null` items remove the base
+    /// item at the same index.
+    pub fn with_null_deletes(mut self, null_deletes: bool) -> Self {
+        self.null_deletes = null_deletes;
+        self
+    }
+
+    /// Uses a different strategy for the array at `pointer`, a json pointer made of object keys
+    /// only, e.g. `/spec/source/helm/parameters`. Array indices are left out so the override
+    /// applies to every element of enclosing arrays.
+    pub fn with_path(mut self, pointer: &str, arrays: ArrayMerge) -> Self {
+        self.paths.push((pointer_segments(pointer), arrays));
+        self
+    }
+
+    fn arrays_at(&self, path: &[String]) -> &ArrayMerge {
+        self.paths
+            .iter()
+            .find(|(segments, _)| segments == path)
+            .map(|(_, arrays)| arrays)
+            .unwrap_or(&self.arrays)
+    }
+}
+
+/// Splits a json pointer into its unescaped segments
+pub(crate) fn pointer_segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Sentinel value deleting the key it is set on, or with the `index` strategy the base item at
+/// the same index. Other array strategies drop it.
+pub const DELETE: &str = "~delete";
+/// Overlay key holding a patch directive: `replace` replaces the base value instead of merging
+/// into it, `delete` removes it. Inside an array, an item only containing `$patch: replace`
+/// replaces the whole base array and an item with `$patch: delete` removes the base items
+/// matching its other keys, or with the `index` strategy the base item at the same index.
+const PATCH: &str = "$patch";
+/// Overlay key listing the keys of an object to keep, any other base key is removed
+const RETAIN_KEYS: &str = "$retainKeys";
+/// Prefix of overlay keys giving the order of the items of an array, e.g.
+/// `$setElementOrder/parameters`
+const SET_ELEMENT_ORDER: &str = "$setElementOrder/";
+
+/// Where an item of a merged array comes from, by its index in the base or overlay array
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Origin {
+    Base(usize),
+    Overlay(usize),
+    /// An overlay item merged into a base item
+    Merged(usize, usize),
+}
+
+impl Origin {
+    fn base(self) -> Option<usize> {
+        match self {
+            Origin::Base(i) | Origin::Merged(i, _) => Some(i),
+            Origin::Overlay(_) => None,
+        }
+    }
+
+    fn overlay(self) -> Option<usize> {
+        match self {
+            Origin::Overlay(i) | Origin::Merged(_, i) => Some(i),
+            Origin::Base(_) => None,
+        }
+    }
+}
+
+/// Where the array items of a merge end up and which values the overlay changed, so that what
+/// is attached to values by path, e.g. comments, can follow them. Paths address array items
+/// with `$<index>` segments.
+#[derive(Debug, Default)]
+pub(crate) struct Moves {
+    /// Origins of the items of each merged array, with the path of the array in the base and in
+    /// the overlay
+    arrays: Vec<(Vec<String>, Vec<String>, Vec<Origin>)>,
+    /// Base paths of the values the overlay set to something else
+    changed: HashSet<Vec<String>>,
+    base_path: Vec<String>,
+    overlay_path: Vec<String>,
+}
+
+impl Moves {
+    /// Returns the path after the merge of the value at `path` in the base, `None` if the value
+    /// was removed
+    pub(crate) fn base_path(&self, path: &[String]) -> Option<Vec<String>> {
+        self.follow(path, |(base, _, _)| base, Origin::base)
+    }
+
+    /// Returns the path after the merge of the value at `path` in the overlay, `None` if the
+    /// value didn't make it into the result
+    pub(crate) fn overlay_path(&self, path: &[String]) -> Option<Vec<String>> {
+        self.follow(path, |(_, overlay, _)| overlay, Origin::overlay)
+    }
+
+    /// Whether the overlay set the value at `path` in the base to something else
+    pub(crate) fn changed(&self, path: &[String]) -> bool {
+        self.changed.contains(path)
+    }
+
+    fn follow(
+        &self,
+        path: &[String],
+        array_path: impl Fn(&(Vec<String>, Vec<String>, Vec<Origin>)) -> &Vec<String>,
+        index: impl Fn(Origin) -> Option<usize>,
+    ) -> Option<Vec<String>> {
+        let mut followed = Vec::with_capacity(path.len());
+        for (depth, segment) in path.iter().enumerate() {
+            let origins = self
+                .arrays
+                .iter()
+                .find(|array| array_path(array)[..] == path[..depth])
+                .map(|(_, _, origins)| origins);
+            let item = segment.strip_prefix('$').and_then(|i| i.parse().ok());
+            match (origins, item) {
+                (Some(origins), Some(i)) => {
+                    let j = origins
+                        .iter()
+                        .position(|origin| index(*origin) == Some(i))?;
+                    followed.push(format!("${}", j));
+                }
+                _ => followed.push(segment.clone()),
+            }
+        }
+        Some(followed)
+    }
+
+    fn push(&mut self, base: String, overlay: String) {
+        self.base_path.push(base);
+        self.overlay_path.push(overlay);
+    }
+
+    fn pop(&mut self) {
+        self.base_path.pop();
+        self.overlay_path.pop();
+    }
+
+    /// Records the origins of the items of the array being merged
+    fn record(&mut self, origins: Vec<Origin>) {
+        let (base_path, overlay_path) = (&self.base_path, &self.overlay_path);
+        match self
+            .arrays
+            .iter_mut()
+            .find(|(base, _, _)| base == base_path)
+        {
+            Some((_, _, recorded)) => *recorded = origins,
+            None => self
+                .arrays
+                .push((base_path.clone(), overlay_path.clone(), origins)),
+        }
+    }
+
+    /// Origins of the items of the array being merged, the base items if it isn't recorded
+    fn origins(&self, len: usize) -> Vec<Origin> {
+        self.arrays
+            .iter()
+            .find(|(base, _, _)| *base == self.base_path)
+            .map(|(_, _, origins)| origins.clone())
+            .unwrap_or_else(|| (0..len).map(Origin::Base).collect())
+    }
+}
+
+/// Deep merges `overlay` into `base`. Objects are merged key by key, arrays according to the
+/// strategy and any other value in the overlay replaces the base value. Merge directives in the
+/// overlay are interpreted and never end up in `base`. `moves` records where array items end
+/// up and which values changed.
+pub(crate) fn merge_values(
+    base: &mut Value,
+    overlay: Value,
+    strategy: &MergeStrategy,
+    path: &mut Vec<String>,
+    moves: &mut Moves,
+) {
+    if is_deletion(&overlay, strategy) {
+        *base = Value::Null;
+        moves.changed.insert(moves.base_path.clone());
+        return;
+    }
+    match (base, overlay) {
+        (Value::Object(base_obj), Value::Object(overlay_obj)) => {
+            merge_objects(base_obj, overlay_obj, strategy, path, moves);
+        }
+        (Value::Array(base_arr), Value::Array(overlay_arr)) => {
+            merge_arrays(base_arr, overlay_arr, strategy, path, moves);
+        }
+        (base, overlay) => {
+            let value = strip_directives(overlay, strategy).unwrap_or(Value::Null);
+            if *base != value {
+                moves.changed.insert(moves.base_path.clone());
+            }
+            *base = value;
+        }
+    }
+}
+
+fn merge_objects(
+    base: &mut Map<String, Value>,
+    mut overlay: Map<String, Value>,
+    strategy: &MergeStrategy,
+    path: &mut Vec<String>,
+    moves: &mut Moves,
+) {
+    if let Some(Value::String(patch)) = overlay.shift_remove(PATCH) {
+        if patch == "replace" {
+            *base = strip_object(overlay, strategy);
+            return;
+        }
+    }
+    let retain_keys = overlay.shift_remove(RETAIN_KEYS);
+    let order_keys: Vec<String> = overlay
+        .keys()
+        .filter(|key| key.starts_with(SET_ELEMENT_ORDER))
+        .cloned()
+        .collect();
+    let mut element_orders = Vec::new();
+    for key in order_keys {
+        if let Some(order) = overlay.shift_remove(&key) {
+            element_orders.push((key[SET_ELEMENT_ORDER.len()..].to_owned(), order));
+        }
+    }
+
+    for (key, value) in overlay {
+        if is_deletion(&value, strategy) {
+            base.shift_remove(&key);
+            continue;
+        }
+        path.push(key.clone());
+        moves.push(key.clone(), key.clone());
+        if let Some(base_value) = base.get_mut(&key) {
+            merge_values(base_value, value, strategy, path, moves);
+        } else if let Some(value) = strip_directives(value, strategy) {
+            base.insert(key, value);
+        }
+        moves.pop();
+        path.pop();
+    }
+
+    if let Some(Value::Array(keys)) = retain_keys {
+        base.retain(|key, _| keys.iter().any(|retained| retained.as_str() == Some(key)));
+    }
+    for (field, order) in element_orders {
+        if let (Some(Value::Array(arr)), Value::Array(order)) = (base.get_mut(&field), order) {
+            moves.push(field.clone(), field);
+            let origins = moves.origins(arr.len());
+            let positions = set_element_order(arr, &order);
+            moves.record(positions.into_iter().map(|i| origins[i]).collect());
+            moves.pop();
+        }
+    }
+}
+
+fn merge_arrays(
+    base: &mut Vec<Value>,
+    overlay: Vec<Value>,
+    strategy: &MergeStrategy,
+    path: &mut Vec<String>,
+    moves: &mut Moves,
+) {
+    let mut arrays = strategy.arrays_at(path).clone();
+    let by_index = arrays == ArrayMerge::ByIndex;
+    let mut origins: Vec<Origin> = (0..base.len()).map(Origin::Base).collect();
+    let mut items = Vec::new();
+    for (i, item) in overlay.into_iter().enumerate() {
+        match patch_directive(&item) {
+            Some("replace") if item.as_object().is_some_and(|obj| obj.len() == 1) => {
+                arrays = ArrayMerge::Replace;
+            }
+            // by index, deletions remove the base item at their position, see below
+            Some("delete") if !by_index => {
+                let mut pattern = item;
+                if let Value::Object(obj) = &mut pattern {
+                    obj.shift_remove(PATCH);
+                    // an empty pattern would match, and delete, every item
+                    if obj.is_empty() {
+                        continue;
+                    }
+                }
+                let mut j = 0;
+                while j < base.len() {
+                    if matches(&base[j], &pattern) {
+                        base.remove(j);
+                        origins.remove(j);
+                    } else {
+                        j += 1;
+                    }
+                }
+            }
+            _ => items.push((i, item)),
+        }
+    }
+
+    match arrays {
+        ArrayMerge::Replace => (*base, origins) = strip_items(items, strategy),
+        ArrayMerge::Append => {
+            let (items, added) = strip_items(items, strategy);
+            base.extend(items);
+            origins.extend(added);
+        }
+        ArrayMerge::Prepend => {
+            let (items, added) = strip_items(items, strategy);
+            base.splice(0..0, items);
+            origins.splice(0..0, added);
+        }
+        ArrayMerge::ByIndex => {
+            let len = base.len();
+            let mut deleted = Vec::new();
+            for (position, (i, item)) in items.into_iter().enumerate() {
+                if is_deletion(&item, strategy) {
+                    if position < len {
+                        deleted.push(position);
+                    }
+                } else if position < len {
+                    merge_item(
+                        &mut base[position],
+                        &mut origins[position],
+                        i,
+                        item,
+                        strategy,
+                        path,
+                        moves,
+                    );
+                } else if let Some(item) = strip_directives(item, strategy) {
+                    base.push(item);
+                    origins.push(Origin::Overlay(i));
+                }
+            }
+            for position in deleted.into_iter().rev() {
+                base.remove(position);
+                origins.remove(position);
+            }
+        }
+        ArrayMerge::ByKey(field) => {
+            for (i, item) in items {
+                let position = item.get(&field).and_then(|key| {
+                    base.iter()
+                        .position(|base_item| base_item.get(&field) == Some(key))
+                });
+                if let Some(position) = position {
+                    merge_item(
+                        &mut base[position],
+                        &mut origins[position],
+                        i,
+                        item,
+                        strategy,
+                        path,
+                        moves,
+                    );
+                } else if let Some(item) = strip_directives(item, strategy) {
+                    base.push(item);
+                    origins.push(Origin::Overlay(i));
+                }
+            }
+        }
+    }
+    moves.record(origins);
+}
+
+/// Merges the overlay item at index `i` into an item of the base array
+fn merge_item(
+    base_item: &mut Value,
+    origin: &mut Origin,
+    i: usize,
+    item: Value,
+    strategy: &MergeStrategy,
+    path: &mut Vec<String>,
+    moves: &mut Moves,
+) {
+    match *origin {
+        Origin::Base(b) => {
+            *origin = Origin::Merged(b, i);
+            moves.push(format!("${}", b), format!("${}", i));
+            merge_values(base_item, item, strategy, path, moves);
+            moves.pop();
+        }
+        // an item the overlay itself added, whose paths aren't followed
+        _ => merge_values(base_item, item, strategy, path, &mut Moves::default()),
+    }
+}
+
+/// Removes the merge directives of overlay items added to an array, dropping deletion markers,
+/// and returns them with their origins
+fn strip_items(items: Vec<(usize, Value)>, strategy: &MergeStrategy) -> (Vec<Value>, Vec<Origin>) {
+    items
+        .into_iter()
+        .filter_map(|(i, item)| Some((strip_directives(item, strategy)?, Origin::Overlay(i))))
+        .unzip()
+}
+
+/// Whether an overlay value marks what it is merged into for deletion
+fn is_deletion(value: &Value, strategy: &MergeStrategy) -> bool {
+    value.as_str() == Some(DELETE)
+        || (strategy.null_deletes && value.is_null())
+        || patch_directive(value) == Some("delete")
+}
+
+/// Returns the `$patch` directive of an overlay value, if any
+fn patch_directive(value: &Value) -> Option<&str> {
+    value.get(PATCH).and_then(Value::as_str)
+}
+
+/// Removes merge directives from an overlay value that has nothing to be merged into. Returns
+/// `None` if the value itself is marked for deletion, which is how deletion markers in arrays
+/// merged other than by index are dropped: they have no base item to delete.
+fn strip_directives(value: Value, strategy: &MergeStrategy) -> Option<Value> {
+    if is_deletion(&value, strategy) {
+        return None;
+    }
+    match value {
+        Value::Object(obj) => Some(Value::Object(strip_object(obj, strategy))),
+        Value::Array(arr) => Some(Value::Array(
+            arr.into_iter()
+                .filter(|item| {
+                    !item
+                        .as_object()
+                        .is_some_and(|obj| obj.len() == 1 && obj.contains_key(PATCH))
+                })
+                .filter_map(|item| strip_directives(item, strategy))
+                .collect(),
+        )),
+        value => Some(value),
+    }
+}
+
+fn strip_object(obj: Map<String, Value>, strategy: &MergeStrategy) -> Map<String, Value> {
+    obj.into_iter()
+        .filter(|(key, value)| {
+            key != PATCH
+                && key != RETAIN_KEYS
+                && !key.starts_with(SET_ELEMENT_ORDER)
+                && !is_deletion(value, strategy)
+        })
+        .filter_map(|(key, value)| Some((key, strip_directives(value, strategy)?)))
+        .collect()
+}
+
+/// Whether an array item matches a pattern. Object patterns match items having the same values
+/// for all the keys of the pattern, anything else has to be equal.
+fn matches(item: &Value, pattern: &Value) -> bool {
+    match pattern {
+        Value::Object(pattern) => pattern
+            .iter()
+            .all(|(key, value)| item.get(key) == Some(value)),
+        pattern => item == pattern,
+    }
+}
+
+/// Moves the items matching `order` to the front of the array, in that order. Items not listed
+/// keep their relative order after them. Returns the previous index of each item.
+fn set_element_order(arr: &mut Vec<Value>, order: &[Value]) -> Vec<usize> {
+    let mut positions: Vec<usize> = Vec::new();
+    for pattern in order {
+        let position =
+            (0..arr.len()).find(|i| !positions.contains(i) && matches(&arr[*i], pattern));
+        positions.extend(position);
+    }
+    let rest: Vec<usize> = (0..arr.len()).filter(|i| !positions.contains(i)).collect();
+    positions.extend(rest);
+    let mut items: Vec<Option<Value>> = std::mem::take(arr).into_iter().map(Some).collect();
+    *arr = positions
+        .iter()
+        .map(|&i| items[i].take().expect("every item is moved once"))
+        .collect();
+    positions
+}
@@ -0,0 +1,160 @@
+use crate::config::Project;
+use crate::error::Error;
+use crate::file::{self, File, LoadOptions};
+use crate::merge::MergeStrategy;
+use crate::schema;
+use crate::state::template_file;
+use jsonschema::Validator;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Renders a template by applying each overlay, in order, on top of the template's base file.
+/// Base and overlays may be multi-document yaml streams, see `merge_documents`, read as set by
+/// `options`.
+pub fn render_template(
+    project: &Project,
+    template: &str,
+    overlays: &[String],
+    strategy: &MergeStrategy,
+    options: &LoadOptions,
+) -> Result<Vec<File>, Error> {
+    let load = |path: &Path| File::load_documents_with(path, options);
+    let mut rendered = load(&template_file(&project.template_dir(template), "base"))?;
+    for overlay in overlays {
+        let path = overlay_path(project, template, overlay);
+        merge_documents(&mut rendered, load(&path)?, strategy, &path)?;
+    }
+    Ok(rendered)
+}
+
+/// Merges overlay documents into base documents. Each overlay document is merged into the base
+/// documents it targets: with a single base document, the base document if it has the same
+/// `kind` as the overlay document, or either has none, otherwise those with the same `kind` and
+/// `metadata.name` as the overlay document where it sets them. An overlay document targeting no
+/// base document is an error, reported for `path`, the overlay file.
+pub fn merge_documents(
+    base: &mut [File],
+    overlay: Vec<File>,
+    strategy: &MergeStrategy,
+    path: &Path,
+) -> Result<(), Error> {
+    let single = base.len() == 1;
+    for (index, document) in overlay.into_iter().enumerate() {
+        let targets: Vec<usize> = (0..base.len())
+            .filter(|&i| {
+                if single {
+                    same_kind(document.data(), base[i].data())
+                } else {
+                    targets_document(document.data(), base[i].data())
+                }
+            })
+            .collect();
+        if targets.is_empty() {
+            return Err(Error::UnmatchedDocument {
+                path: path.to_path_buf(),
+                index,
+            });
+        }
+        for i in targets {
+            let document = File::new(document.data().clone())
+                .with_comments(document.comments().clone())
+                .with_sources(document.sources().clone());
+            base[i].merge_with(document, strategy);
+        }
+    }
+    Ok(())
+}
+
+/// Whether an overlay document and a base document don't set different kinds
+fn same_kind(overlay: &Value, base: &Value) -> bool {
+    match (overlay.get("kind"), base.get("kind")) {
+        (Some(overlay), Some(base)) => overlay == base,
+        _ => true,
+    }
+}
+
+/// Whether an overlay document targets a base document
+fn targets_document(overlay: &Value, base: &Value) -> bool {
+    let kind = overlay.get("kind");
+    let name = overlay.pointer("/metadata/name");
+    (kind.is_some() || name.is_some())
+        && kind.is_none_or(|kind| base.get("kind") == Some(kind))
+        && name.is_none_or(|name| base.pointer("/metadata/name") == Some(name))
+}
+
+/// Loads the schema of a template.
+pub fn template_schema(project: &Project, template: &str) -> Result<File, Error> {
+    let schema = File::load(template_file(&project.template_dir(template), "schema"))?;
+    Ok(schema)
+}
+
+/// Loads the schema of a template, checks it with `schema::check` and compiles it into a
+/// validator
+pub fn template_validator(project: &Project, template: &str) -> Result<(File, Validator), Error> {
+    let schema_path = template_file(&project.template_dir(template), "schema");
+    let schema = File::load(&schema_path)?;
+    let validator = schema::validator(&schema, &schema_path)?;
+    Ok((schema, validator))
+}
+
+/// Lists the templates of a project, sorted by name
+pub fn template_names(project: &Project) -> Result<Vec<String>, Error> {
+    folder_entries(&project.templates_dir(), true)
+}
+
+/// Lists the overlay files of a template, sorted by file name. A template without an overlays
+/// folder has none.
+pub fn overlay_names(project: &Project, template: &str) -> Result<Vec<String>, Error> {
+    let overlays_folder = project.overlays_dir(template);
+    if !overlays_folder.is_dir() {
+        return Ok(Vec::new());
+    }
+    folder_entries(&overlays_folder, false)
+}
+
+/// Lists the overlays a template is rendered with one at a time to check it, `None` standing for
+/// the base alone when the template has no overlays
+pub fn overlay_combinations(
+    project: &Project,
+    template: &str,
+) -> Result<Vec<Option<String>>, Error> {
+    let overlays = overlay_names(project, template)?;
+    if overlays.is_empty() {
+        return Ok(vec![None]);
+    }
+    Ok(overlays.into_iter().map(Some).collect())
+}
+
+/// Returns the sorted names of the folders, or files, in a folder
+fn folder_entries(folder: &Path, folders: bool) -> Result<Vec<String>, Error> {
+    let io_error = |source| file::Error::Io {
+        path: folder.to_path_buf(),
+        source,
+    };
+    let mut names = Vec::new();
+    for entry in fs::read_dir(folder).map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        if entry.path().is_dir() == folders {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Resolves an overlay argument. Paths, arguments with more than one component such as
+/// `./prod.yaml`, are used as is, names are looked up, with or without extension, in the
+/// overlays folder of the template.
+fn overlay_path(project: &Project, template: &str, overlay: &str) -> PathBuf {
+    let path = Path::new(overlay);
+    if path.components().count() > 1 {
+        return path.to_path_buf();
+    }
+    let overlays_folder = project.overlays_dir(template);
+    if Path::new(overlay).extension().is_none() {
+        template_file(&overlays_folder, overlay)
+    } else {
+        overlays_folder.join(overlay)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::file::File;
+use crate::sources::SourceLocation;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::Validator;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// A value of a rendered document not matching the schema
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationIssue {
+    /// Index of the document in the rendered stream
+    pub document: usize,
+    /// Json pointer to the invalid value
+    pub pointer: String,
+    /// Schema keyword that failed, e.g. `type` or `required`
+    pub keyword: String,
+    pub message: String,
+    /// File and line the invalid value, or its closest enclosing value, was read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
+}
+
+impl fmt::Display for ValidationIssue {
+    /// Writes the error on a single line, prefixed with its source when known
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(source) = &self.source {
+            write!(f, "{}: ", source)?;
+        }
+        write!(f, "{}: {}", self.pointer, self.message)
+    }
+}
+
+/// Every schema error of a set of rendered documents
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub errors: Vec<ValidationIssue>,
+    /// Issues that don't make the report invalid, e.g. unknown schema keywords
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ValidationIssue>,
+    #[serde(skip)]
+    documents: usize,
+}
+
+impl ValidationReport {
+    pub fn new(validator: &Validator, documents: &[File]) -> Self {
+        let mut errors = Vec::new();
+        for (i, document) in documents.iter().enumerate() {
+            for error in validator.iter_errors(document.data()) {
+                let mut pointer = error.instance_path.as_str().to_owned();
+                // point at the unexpected key, not the object holding it
+                if let ValidationErrorKind::AdditionalProperties { unexpected } = &error.kind {
+                    if let Some(key) = unexpected.first() {
+                        pointer.push('/');
+                        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                    }
+                }
+                let keyword = error
+                    .schema_path
+                    .as_str()
+                    .rsplit('/')
+                    .find(|segment| segment.parse::<usize>().is_err())
+                    .unwrap_or_default()
+                    .to_owned();
+                let source = document
+                    .sources()
+                    .locate(&pointer, document.data())
+                    .cloned();
+                errors.push(ValidationIssue {
+                    document: i,
+                    pointer,
+                    keyword,
+                    message: error.to_string(),
+                    source,
+                });
+            }
+        }
+        ValidationReport {
+            errors,
+            warnings: Vec::new(),
+            documents: documents.len(),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report is always serializable")
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    /// Lists the errors, then the warnings, for humans, the document only being given for
+    /// multi-document renders
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issues = self
+            .errors
+            .iter()
+            .map(|error| ("Error", error))
+            .chain(self.warnings.iter().map(|warning| ("Warning", warning)));
+        for (i, (level, error)) in issues.enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}: {}", level, error.message)?;
+            if self.documents > 1 {
+                writeln!(f, "Document: {}", error.document)?;
+            }
+            writeln!(f, "Location: {}", error.pointer)?;
+            writeln!(f, "Keyword: {}", error.keyword)?;
+            if let Some(source) = &error.source {
+                writeln!(f, "Source: {}", source)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a validation report is printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!(
+                "unknown report format {}, expected text or json",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportFormat::Text => write!(f, "text"),
+            ReportFormat::Json => write!(f, "json"),
+        }
+    }
+}
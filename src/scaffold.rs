@@ -0,0 +1,293 @@
+use crate::comments::Comments;
+use crate::config::{self, Project};
+use crate::error::Error;
+use crate::file::{self, File, Format, LoadOptions};
+use crate::init;
+use crate::merge::MergeStrategy;
+use crate::render;
+use crate::report::ValidationReport;
+use crate::schema::resolve;
+use jsonschema::Validator;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Builds a skeleton overlay holding every property `schema` requires that `base` doesn't set,
+/// recursing into objects. Values are the schema `default`, the first `enum` value or a
+/// placeholder of the expected type, each preceded by a comment giving the type, allowed values
+/// and description.
+pub fn overlay_skeleton(base: &Value, schema: &Value) -> File {
+    let mut skeleton = Map::new();
+    let mut comments = Comments::default();
+    fill_required(
+        &mut skeleton,
+        Some(base),
+        schema,
+        schema,
+        &mut Vec::new(),
+        &mut comments,
+    );
+    File::new(Value::Object(skeleton)).with_comments(comments)
+}
+
+fn fill_required(
+    skeleton: &mut Map<String, Value>,
+    base: Option<&Value>,
+    schema: &Value,
+    root: &Value,
+    path: &mut Vec<String>,
+    comments: &mut Comments,
+) {
+    let schema = resolve(root, schema);
+    let Some(required) = schema.get("required").and_then(Value::as_array) else {
+        return;
+    };
+    let empty = Value::Object(Map::new());
+    for key in required.iter().filter_map(Value::as_str) {
+        let property = schema
+            .get("properties")
+            .and_then(|properties| properties.get(key))
+            .unwrap_or(&empty);
+        path.push(key.to_owned());
+        match base.and_then(|base| base.get(key)) {
+            Some(base_value) if base_value.is_object() => {
+                let mut nested = Map::new();
+                fill_required(
+                    &mut nested,
+                    Some(base_value),
+                    property,
+                    root,
+                    path,
+                    comments,
+                );
+                if !nested.is_empty() {
+                    skeleton.insert(key.to_owned(), Value::Object(nested));
+                }
+            }
+            Some(_) => {}
+            None => {
+                let property = resolve(root, property);
+                comments.push_before(path.clone(), &describe(property));
+                let value = placeholder(property, root, path, comments);
+                skeleton.insert(key.to_owned(), value);
+            }
+        }
+        path.pop();
+    }
+}
+
+fn placeholder(
+    schema: &Value,
+    root: &Value,
+    path: &mut Vec<String>,
+    comments: &mut Comments,
+) -> Value {
+    if let Some(default) = schema.get("default") {
+        return default.clone();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.clone();
+    }
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|values| values.first())
+    {
+        return first.clone();
+    }
+    match first_type(schema) {
+        Some("object") => {
+            let mut nested = Map::new();
+            fill_required(&mut nested, None, schema, root, path, comments);
+            Value::Object(nested)
+        }
+        Some("array") => Value::Array(Vec::new()),
+        Some("string") => Value::String(String::new()),
+        Some("integer") | Some("number") => Value::from(0),
+        Some("boolean") => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
+
+/// Returns the expected type of a schema, the first one other than `null` if it allows several
+fn first_type(schema: &Value) -> Option<&str> {
+    match schema.get("type")? {
+        Value::String(name) => Some(name),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|name| *name != "null"),
+        _ => None,
+    }
+}
+
+/// Describes what a property expects, e.g. `string, one of: Always, Never - the pull policy`
+fn describe(schema: &Value) -> String {
+    let mut description = match schema.get("type") {
+        Some(Value::String(name)) => name.clone(),
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" | "),
+        _ => "any".to_owned(),
+    };
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        let values: Vec<String> = values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+            .collect();
+        description.push_str(", one of: ");
+        description.push_str(&values.join(", "));
+    }
+    let text = schema
+        .get("description")
+        .or_else(|| schema.get("title"))
+        .and_then(Value::as_str)
+        .and_then(|text| text.lines().next());
+    if let Some(text) = text {
+        description.push_str(" - ");
+        description.push_str(text.trim());
+    }
+    description
+}
+
+/// Writes the skeleton overlay of a template to `overlays/<name>.yaml` in its folder and returns
+/// the path written. An existing overlay is never overwritten, and `name` has to be a plain file
+/// name.
+pub fn new_overlay(project: &Project, template: &str, name: &str) -> Result<PathBuf, Error> {
+    config::check_name(name)?;
+    let base = render::render_template(
+        project,
+        template,
+        &[],
+        &MergeStrategy::default(),
+        &LoadOptions::default(),
+    )?;
+    let base = base.first().map_or(&Value::Null, File::data);
+    let schema = render::template_schema(project, template)?;
+    let overlays_folder = project.overlays_dir(template);
+    let overlay_path = if Path::new(name).extension().is_some() {
+        overlays_folder.join(name)
+    } else {
+        overlays_folder.join(format!("{}.yaml", name))
+    };
+    if overlay_path.exists() {
+        return Err(Error::AlreadyExists { path: overlay_path });
+    }
+    fs::create_dir_all(&overlays_folder).map_err(|source| file::Error::Io {
+        path: overlays_folder.clone(),
+        source,
+    })?;
+    overlay_skeleton(base, schema.data()).save(&overlay_path)?;
+    Ok(overlay_path)
+}
+
+/// Creates a template from existing files, `base` and `overlays` and a `schema` or, without
+/// one, a schema inferred from the base rendered with each overlay. A given schema has to accept
+/// these renders, or the base alone without overlays, like `validate` checks templates. Files
+/// are written in the format of the project, converted if needed, and copied as is otherwise.
+/// An existing template is never overwritten.
+pub fn import_template(
+    project: &Project,
+    name: &str,
+    base: &Path,
+    schema: Option<&Path>,
+    overlays: &[PathBuf],
+) -> Result<PathBuf, Error> {
+    let template_folder = init::new_template_folder(project, name)?;
+    let format = project.config().format;
+    let file_name = |stem: &str| format!("{}.{}", stem, format.name());
+
+    let base_documents = File::load_documents(base)?;
+    let mut files = vec![(
+        template_folder.join(file_name("base")),
+        import_file(base, &base_documents, format)?,
+    )];
+    let overlays_folder = project.overlays_dir(name);
+    let mut samples = Vec::new();
+    for overlay in overlays {
+        let stem = overlay.file_stem().unwrap_or_default().to_string_lossy();
+        let path = overlays_folder.join(file_name(&stem));
+        if files.iter().any(|(known, _)| *known == path) {
+            return Err(Error::DuplicateOverlay { path });
+        }
+        let documents = File::load_documents(overlay)?;
+        files.push((path, import_file(overlay, &documents, format)?));
+        let mut rendered: Vec<File> = base_documents
+            .iter()
+            .map(|document| File::new(document.data().clone()))
+            .collect();
+        render::merge_documents(&mut rendered, documents, &MergeStrategy::default(), overlay)?;
+        samples.push((overlay.as_path(), rendered));
+    }
+    if samples.is_empty() {
+        samples.push((base, base_documents));
+    }
+    let schema_content = match schema {
+        Some(schema) => {
+            let schema_file = File::load(schema)?;
+            let validator = crate::schema::validator(&schema_file, schema)?;
+            for (path, documents) in &samples {
+                check_sample(&validator, path, documents)?;
+            }
+            import_file(schema, &[schema_file], format)?
+        }
+        None => {
+            let samples: Vec<&Value> = samples
+                .iter()
+                .flat_map(|(_, documents)| documents)
+                .map(File::data)
+                .collect();
+            File::new(crate::schema::infer(&samples)).to_format_string(format)?
+        }
+    };
+    files.push((template_folder.join(file_name("schema")), schema_content));
+
+    write_files(files)?;
+    Ok(template_folder)
+}
+
+/// Fails with the errors `validator` finds in documents read from, or rendered with, `path`
+fn check_sample(validator: &Validator, path: &Path, documents: &[File]) -> Result<(), Error> {
+    let report = ValidationReport::new(validator, documents);
+    if report.is_valid() {
+        return Ok(());
+    }
+    Err(Error::Invalid {
+        path: path.to_path_buf(),
+        message: report
+            .errors
+            .iter()
+            .map(|error| format!("\n  {}", error))
+            .collect(),
+    })
+}
+
+/// Writes the files of a new template, ending them with a newline
+pub(crate) fn write_files(files: Vec<(PathBuf, String)>) -> Result<(), Error> {
+    for (path, mut content) in files {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        init::write(&path, &content)?;
+    }
+    Ok(())
+}
+
+/// Returns the content of an imported file in `format`, the file itself if it already is in it
+fn import_file(path: &Path, documents: &[File], format: Format) -> Result<String, Error> {
+    if Format::from_path(path) == Some(format) {
+        let content = fs::read_to_string(path).map_err(|source| file::Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        return Ok(content);
+    }
+    let content = file::documents_to_format_string(documents, format)
+        .map_err(|error| error.with_path(path))?;
+    Ok(content)
+}
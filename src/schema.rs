@@ -0,0 +1,442 @@
+use crate::error::Error;
+use crate::file::File;
+use crate::report::{ValidationIssue, ValidationReport};
+use jsonschema::{Draft, Validator};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+/// Keywords of every json schema draft, from draft 4 to 2020-12
+const KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "id",
+    "$ref",
+    "$dynamicRef",
+    "$dynamicAnchor",
+    "$recursiveRef",
+    "$recursiveAnchor",
+    "$anchor",
+    "$vocabulary",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "type",
+    "enum",
+    "const",
+    "multipleOf",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "maxContains",
+    "minContains",
+    "maxProperties",
+    "minProperties",
+    "required",
+    "dependentRequired",
+    "dependentSchemas",
+    "dependencies",
+    "properties",
+    "patternProperties",
+    "additionalProperties",
+    "propertyNames",
+    "unevaluatedProperties",
+    "items",
+    "additionalItems",
+    "prefixItems",
+    "contains",
+    "unevaluatedItems",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+    "if",
+    "then",
+    "else",
+    "format",
+    "contentEncoding",
+    "contentMediaType",
+    "contentSchema",
+];
+/// Keywords holding a single subschema
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "additionalProperties",
+    "items",
+    "additionalItems",
+    "contains",
+    "propertyNames",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "not",
+    "if",
+    "then",
+    "else",
+    "contentSchema",
+];
+/// Keywords holding an array of subschemas
+const SCHEMA_ARRAY_KEYWORDS: &[&str] = &["allOf", "anyOf", "oneOf", "prefixItems", "items"];
+/// Keywords holding an object of subschemas
+const SCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "definitions",
+    "$defs",
+    "dependentSchemas",
+    "dependencies",
+];
+
+/// Follows `$ref`s to local definitions, e.g. `#/definitions/Metadata`, until reaching a schema
+/// without one. Remote or unresolvable references are returned as is.
+pub fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    let mut schema = schema;
+    // bounded so that circular references can't loop forever
+    for _ in 0..32 {
+        let Some(target) = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| root.pointer(pointer))
+        else {
+            break;
+        };
+        schema = target;
+    }
+    schema
+}
+
+/// Orders the keys of every object in `value` the way the matching schema lists them in
+/// `properties`. Keys the schema doesn't list keep their current order after the listed ones.
+pub fn canonical_order(value: &mut Value, root: &Value) {
+    order_by_schema(value, root, root);
+}
+
+fn order_by_schema(value: &mut Value, schema: &Value, root: &Value) {
+    let schema = resolve(root, schema);
+    match value {
+        Value::Object(obj) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let mut ordered = Map::new();
+            if let Some(properties) = properties {
+                for key in properties.keys() {
+                    if let Some(item) = obj.shift_remove(key) {
+                        ordered.insert(key.clone(), item);
+                    }
+                }
+            }
+            ordered.append(obj);
+            for (key, item) in ordered.iter_mut() {
+                let property = properties
+                    .and_then(|properties| properties.get(key))
+                    .or_else(|| schema.get("additionalProperties"));
+                if let Some(property) = property {
+                    order_by_schema(item, property, root);
+                }
+            }
+            *obj = ordered;
+        }
+        Value::Array(arr) => {
+            if let Some(items) = schema.get("items") {
+                for item in arr.iter_mut() {
+                    order_by_schema(item, items, root);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Largest number of distinct strings inferred as an enum
+const ENUM_LIMIT: usize = 5;
+
+/// Drafts a schema from sample documents, e.g. a base rendered with each of its overlays. Keys
+/// found in every sample object are required, strings taking a few distinct values become enums
+/// and array items are inferred from the items of every sample array.
+pub fn infer(samples: &[&Value]) -> Value {
+    let mut schema = Map::new();
+    schema.insert(
+        "$schema".to_owned(),
+        Value::String("http://json-schema.org/draft-07/schema#".to_owned()),
+    );
+    if let Value::Object(inferred) = infer_values(samples) {
+        schema.extend(inferred);
+    }
+    Value::Object(schema)
+}
+
+fn infer_values(samples: &[&Value]) -> Value {
+    let mut schema = Map::new();
+    let mut types: Vec<&str> = Vec::new();
+    for sample in samples {
+        let name = match sample {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        if !types.contains(&name) {
+            types.push(name);
+        }
+    }
+    if types.contains(&"number") {
+        types.retain(|name| *name != "integer");
+    }
+    match types.as_slice() {
+        [] => {}
+        [name] => {
+            schema.insert("type".to_owned(), Value::String((*name).to_owned()));
+        }
+        names => {
+            schema.insert("type".to_owned(), json!(names));
+        }
+    }
+
+    let objects: Vec<&Map<String, Value>> = samples
+        .iter()
+        .filter_map(|sample| sample.as_object())
+        .collect();
+    if !objects.is_empty() {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut keys: Vec<&String> = Vec::new();
+        for key in objects.iter().flat_map(|obj| obj.keys()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        for key in keys {
+            let values: Vec<&Value> = objects.iter().filter_map(|obj| obj.get(key)).collect();
+            if values.len() == objects.len() {
+                required.push(Value::String(key.clone()));
+            }
+            properties.insert(key.clone(), infer_values(&values));
+        }
+        schema.insert("properties".to_owned(), Value::Object(properties));
+        if !required.is_empty() {
+            schema.insert("required".to_owned(), Value::Array(required));
+        }
+    }
+
+    let items: Vec<&Value> = samples
+        .iter()
+        .filter_map(|sample| sample.as_array())
+        .flatten()
+        .collect();
+    if !items.is_empty() {
+        schema.insert("items".to_owned(), infer_values(&items));
+    }
+
+    if types == ["string"] {
+        let mut values: Vec<&Value> = Vec::new();
+        for sample in samples {
+            if !values.contains(sample) {
+                values.push(sample);
+            }
+        }
+        if (2..=ENUM_LIMIT).contains(&values.len()) {
+            schema.insert("enum".to_owned(), json!(values));
+        }
+    }
+    Value::Object(schema)
+}
+
+/// Fills in the properties missing from every object in `value` with the `default` the matching
+/// schema gives them. Missing objects without a default are only created when required, and only
+/// if one of their properties has a default.
+pub fn apply_defaults(value: &mut Value, root: &Value) {
+    defaults_from_schema(value, root, root);
+}
+
+fn defaults_from_schema(value: &mut Value, schema: &Value, root: &Value) {
+    let schema = resolve(root, schema);
+    match value {
+        Value::Object(obj) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, property) in properties.into_iter().flatten() {
+                if obj.contains_key(key) {
+                    continue;
+                }
+                let property = resolve(root, property);
+                if let Some(default) = property.get("default") {
+                    obj.insert(key.clone(), default.clone());
+                } else if is_required(schema, key) && property.get("properties").is_some() {
+                    let mut missing = Value::Object(Map::new());
+                    defaults_from_schema(&mut missing, property, root);
+                    if missing
+                        .as_object()
+                        .is_some_and(|missing| !missing.is_empty())
+                    {
+                        obj.insert(key.clone(), missing);
+                    }
+                }
+            }
+            for (key, item) in obj.iter_mut() {
+                let property = properties
+                    .and_then(|properties| properties.get(key))
+                    .or_else(|| schema.get("additionalProperties"));
+                if let Some(property) = property {
+                    defaults_from_schema(item, property, root);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            if let Some(items) = schema.get("items") {
+                for item in arr.iter_mut() {
+                    defaults_from_schema(item, items, root);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_required(schema: &Value, key: &str) -> bool {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .is_some_and(|required| required.iter().any(|name| name.as_str() == Some(key)))
+}
+
+/// Checks a schema against the metaschema of the draft its `$schema` declares, and reports
+/// local `$ref`s pointing nowhere. Keywords no draft knows, except `x-` extensions, are
+/// reported as warnings: json schema allows them, but they often are typos.
+pub fn check(schema: &File) -> ValidationReport {
+    let data = schema.data();
+    let mut report = match metaschema_validator(data) {
+        Ok(validator) => ValidationReport::new(&validator, std::slice::from_ref(schema)),
+        Err(message) => {
+            let mut report = ValidationReport::default();
+            report
+                .errors
+                .push(issue(schema, "/$schema", "$schema", message));
+            report
+        }
+    };
+    let mut path = Vec::new();
+    check_keywords(schema, data, &mut path, &mut report);
+    report
+}
+
+/// Checks a schema with `check` and compiles it, ignoring warnings. `path` is the file the
+/// schema was read from.
+pub fn validator(schema: &File, path: &Path) -> Result<Validator, Error> {
+    let report = check(schema);
+    if !report.is_valid() {
+        let message = report
+            .errors
+            .iter()
+            .map(|error| format!("\n  {}", error))
+            .collect::<String>();
+        return Err(Error::InvalidSchema {
+            path: path.to_path_buf(),
+            message,
+        });
+    }
+    jsonschema::validator_for(schema.data()).map_err(|error| Error::InvalidSchema {
+        path: path.to_path_buf(),
+        message: format!("{} at {}", error, error.instance_path),
+    })
+}
+
+fn metaschema_validator(schema: &Value) -> Result<Validator, String> {
+    let draft = Draft::default()
+        .detect(schema)
+        .map_err(|error| error.to_string())?;
+    let uri = match draft {
+        Draft::Draft4 => "http://json-schema.org/draft-04/schema#",
+        Draft::Draft6 => "http://json-schema.org/draft-06/schema#",
+        Draft::Draft7 => "http://json-schema.org/draft-07/schema#",
+        Draft::Draft201909 => "https://json-schema.org/draft/2019-09/schema",
+        _ => "https://json-schema.org/draft/2020-12/schema",
+    };
+    // the metaschemas are bundled with jsonschema, nothing is fetched
+    jsonschema::options()
+        .with_draft(draft)
+        .build(&json!({ "$ref": uri }))
+        .map_err(|error| error.to_string())
+}
+
+fn check_keywords(
+    schema: &File,
+    node: &Value,
+    path: &mut Vec<String>,
+    report: &mut ValidationReport,
+) {
+    let Some(obj) = node.as_object() else {
+        return;
+    };
+    for (key, value) in obj {
+        path.push(key.clone());
+        if !KEYWORDS.contains(&key.as_str()) && !key.starts_with("x-") {
+            let message = format!("unknown keyword {}", key);
+            report
+                .warnings
+                .push(issue(schema, &pointer(path), "unknown", message));
+        }
+        if key == "$ref" {
+            check_ref(schema, value, path, report);
+        }
+        if SCHEMA_KEYWORDS.contains(&key.as_str()) {
+            check_keywords(schema, value, path, report);
+        }
+        if SCHEMA_ARRAY_KEYWORDS.contains(&key.as_str()) {
+            for (i, item) in value.as_array().into_iter().flatten().enumerate() {
+                path.push(i.to_string());
+                check_keywords(schema, item, path, report);
+                path.pop();
+            }
+        }
+        if SCHEMA_MAP_KEYWORDS.contains(&key.as_str()) {
+            for (name, item) in value.as_object().into_iter().flatten() {
+                path.push(name.clone());
+                check_keywords(schema, item, path, report);
+                path.pop();
+            }
+        }
+        path.pop();
+    }
+}
+
+/// Reports local `$ref`s, e.g. `#/definitions/Metadata`, that don't point to a value
+fn check_ref(schema: &File, reference: &Value, path: &[String], report: &mut ValidationReport) {
+    let Some(target) = reference.as_str().and_then(|r| r.strip_prefix('#')) else {
+        return;
+    };
+    if (target.is_empty() || target.starts_with('/')) && schema.data().pointer(target).is_none() {
+        let message = format!("unresolved reference {}", reference);
+        report
+            .errors
+            .push(issue(schema, &pointer(path), "$ref", message));
+    }
+}
+
+fn pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+fn issue(schema: &File, pointer: &str, keyword: &str, message: String) -> ValidationIssue {
+    ValidationIssue {
+        document: 0,
+        pointer: pointer.to_owned(),
+        keyword: keyword.to_owned(),
+        message,
+        source: schema.sources().locate(pointer, schema.data()).cloned(),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::comments::{self, PathTracker};
+use crate::merge::Moves;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// File and line a value was read from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SourceLocation {
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)
+    }
+}
+
+/// Where the keys and array items of a yaml document were read from, addressed by path like
+/// `Comments` does. After merging, values set by an overlay point to the overlay.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sources {
+    entries: HashMap<Vec<String>, SourceLocation>,
+}
+
+impl Sources {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records the lines of a single yaml document starting on line `first_line` of `path`
+    pub fn parse(source: &str, path: &Path, first_line: usize) -> Self {
+        let mut sources = Sources::default();
+        let mut tracker = PathTracker::default();
+        for (i, line) in source.lines().enumerate() {
+            if tracker.in_block_scalar(line) {
+                continue;
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty()
+                || trimmed.starts_with('#')
+                || comments::is_document_marker(trimmed)
+            {
+                continue;
+            }
+            let location = SourceLocation {
+                path: path.to_path_buf(),
+                line: first_line + i,
+            };
+            sources
+                .entries
+                .entry(Vec::new())
+                .or_insert_with(|| location.clone());
+            for node in tracker.track(line).paths {
+                sources
+                    .entries
+                    .entry(node)
+                    .or_insert_with(|| location.clone());
+            }
+        }
+        sources
+    }
+
+    /// Records the lines of every document of a yaml stream
+    pub fn parse_documents(source: &str, path: &Path) -> Vec<Self> {
+        comments::split_documents(source)
+            .iter()
+            .map(|(first_line, document)| Sources::parse(document, path, *first_line))
+            .collect()
+    }
+
+    /// Adds the locations of an overlay that was merged into `merged`, following array items to
+    /// where the merge put them, see `Moves`. Overlay locations replace those of the values they
+    /// set, objects that already were in the base keep theirs.
+    pub(crate) fn merge(&mut self, overlay: Sources, moves: &Moves, merged: &Value) {
+        self.entries = self
+            .entries
+            .drain()
+            .filter_map(|(path, location)| Some((moves.base_path(&path)?, location)))
+            .collect();
+        for (path, location) in overlay.entries {
+            let Some(path) = moves.overlay_path(&path) else {
+                continue;
+            };
+            if self.entries.contains_key(&path)
+                && value_at(merged, &path).is_some_and(Value::is_object)
+            {
+                continue;
+            }
+            self.entries.insert(path, location);
+        }
+    }
+
+    /// Returns where the value at a json pointer in `data` was read from, or the closest
+    /// enclosing value known if it wasn't read from a file, e.g. a missing required property.
+    pub fn locate(&self, pointer: &str, data: &Value) -> Option<&SourceLocation> {
+        let mut path = Vec::new();
+        let mut node = Some(data);
+        for segment in pointer.split('/').skip(1) {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            match node {
+                Some(Value::Array(arr)) => {
+                    node = segment.parse::<usize>().ok().and_then(|i| arr.get(i));
+                    path.push(format!("${}", segment));
+                }
+                Some(value) => {
+                    node = value.get(&segment);
+                    path.push(segment);
+                }
+                None => path.push(segment),
+            }
+        }
+        loop {
+            if let Some(location) = self.entries.get(&path) {
+                return Some(location);
+            }
+            path.pop()?;
+        }
+    }
+}
+
+/// Returns the value at a path of keys and `$<index>` segments
+fn value_at<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |node, segment| {
+        match segment
+            .strip_prefix('$')
+            .and_then(|i| i.parse::<usize>().ok())
+        {
+            Some(i) if node.is_array() => node.get(i),
+            _ => node.get(segment),
+        }
+    })
+}
@@ -1,38 +1,148 @@
-use crate::file::File;
+use crate::config::Project;
+use crate::error::Error;
+use crate::file::{self, File};
+use crate::merge::MergeStrategy;
+use crate::render;
+use crate::report::ValidationIssue;
+use crate::schema;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
 
+/// Returns a sha256 hex digest of a value, used to detect changes between syncs
+fn content_hash(value: &Value) -> String {
+    text_hash(&value.to_string())
+}
+
+/// Returns a sha256 hex digest of some text
+pub(crate) fn text_hash(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the stored hash, or computes it for state files written before hashes were stored
+fn stored_or_computed_hash(hash: &str, compute: impl FnOnce() -> String) -> String {
+    if hash.is_empty() {
+        compute()
+    } else {
+        hash.to_owned()
+    }
+}
+
+/// The documents of a base or overlay file. A single document is stored in `values`, the
+/// documents of a multi-document stream in `documents`, `values` then being null, so that a
+/// document that is an array isn't mistaken for a stream.
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
-struct VersionedTemplate {
+struct StoredDocuments {
     values: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    documents: Option<Vec<Value>>,
+}
+
+impl StoredDocuments {
+    fn load(path: PathBuf) -> Result<Self, Error> {
+        let mut documents = File::load_documents(path)?;
+        let stored = match documents.len() {
+            0 => StoredDocuments {
+                values: Value::Null,
+                documents: None,
+            },
+            1 => StoredDocuments {
+                values: documents.remove(0).into_data(),
+                documents: None,
+            },
+            _ => StoredDocuments {
+                values: Value::Null,
+                documents: Some(documents.into_iter().map(File::into_data).collect()),
+            },
+        };
+        Ok(stored)
+    }
+
+    fn hash(&self) -> String {
+        match &self.documents {
+            Some(documents) => {
+                text_hash(&serde_json::to_string(documents).expect("documents should serialize"))
+            }
+            None => content_hash(&self.values),
+        }
+    }
+
+    fn files(&self) -> Vec<File> {
+        match &self.documents {
+            Some(documents) => documents.iter().cloned().map(File::new).collect(),
+            None => vec![File::new(self.values.clone())],
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+struct VersionedTemplate {
+    #[serde(flatten)]
+    values: StoredDocuments,
+    #[serde(default)]
+    values_hash: String,
     version: u32,
     overlays: Vec<Overlay>,
     schema: Value,
+    #[serde(default)]
+    schema_hash: String,
     created: String,
 }
 
+impl VersionedTemplate {
+    fn new(version: u32, values: StoredDocuments, overlays: Vec<Overlay>, schema: Value) -> Self {
+        VersionedTemplate {
+            values_hash: values.hash(),
+            values,
+            version,
+            overlays,
+            schema_hash: content_hash(&schema),
+            schema,
+            created: chrono::Utc::now().to_string(),
+        }
+    }
+    fn values_hash(&self) -> String {
+        stored_or_computed_hash(&self.values_hash, || self.values.hash())
+    }
+    fn schema_hash(&self) -> String {
+        stored_or_computed_hash(&self.schema_hash, || content_hash(&self.schema))
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 struct Overlay {
     name: String,
-    values: Value,
+    #[serde(flatten)]
+    values: StoredDocuments,
+    #[serde(default)]
+    hash: String,
 }
 
 impl Overlay {
-    pub fn new(name: &str, values: Value) -> Self {
+    fn new(name: &str, values: StoredDocuments) -> Self {
         Overlay {
             name: name.to_owned(),
+            hash: values.hash(),
             values,
         }
     }
+    fn hash(&self) -> String {
+        stored_or_computed_hash(&self.hash, || self.values.hash())
+    }
 }
 
+/// A named use of a template, pinned to one of its versions, rendered with overlays of that
+/// version in order followed by its own inline overlay
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
-struct Implementation {
-    name: String,
-    version: String,
-    overlays: Vec<String>,
-    overlay: Value,
-    created: String,
+pub struct Implementation {
+    pub name: String,
+    pub version: u32,
+    pub overlays: Vec<String>,
+    pub overlay: Value,
+    pub created: String,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -46,15 +156,9 @@ struct TemplateState {
 }
 
 impl TemplateState {
-    fn new(name: &str, values: Value, overlays: Vec<Overlay>, schema: Value) -> Self {
-        let now = chrono::Utc::now().to_string();
-        let current = VersionedTemplate {
-            version: 0,
-            values,
-            overlays,
-            schema,
-            created: now.to_owned(),
-        };
+    fn new(name: &str, values: StoredDocuments, overlays: Vec<Overlay>, schema: Value) -> Self {
+        let current = VersionedTemplate::new(0, values, overlays, schema);
+        let now = current.created.clone();
         TemplateState {
             name: name.to_owned(),
             current,
@@ -65,50 +169,52 @@ impl TemplateState {
         }
     }
 
-    fn values(&self) -> &Value {
-        &self.current.values
-    }
     fn overlays(&self) -> &[Overlay] {
         &self.current.overlays
     }
-    fn schema(&self) -> &Value {
-        &self.current.schema
-    }
-    fn template_has_changed(&self, template: &Value) -> bool {
-        self.current.values.as_str() != template.as_str()
+    fn template_has_changed(&self, template: &VersionedTemplate) -> bool {
+        self.current.values_hash() != template.values_hash()
     }
-    fn schema_has_changed(&self, schema: &Value) -> bool {
-        self.current.schema.as_str() != schema.as_str()
+    fn schema_has_changed(&self, template: &VersionedTemplate) -> bool {
+        self.current.schema_hash() != template.schema_hash()
     }
+    /// Overlays are compared by name and content, so added, removed and renamed overlays count
+    /// as changes. Both lists are expected to be sorted by name.
     fn overlays_have_changed(&self, overlays: &[Overlay]) -> bool {
         if self.current.overlays.len() != overlays.len() {
-            return false;
+            return true;
         }
-        let mut i = 0;
-        while i < self.current.overlays.len() {
-            if self.current.overlays[i].values.as_str() != overlays[i].values.as_str() {
-                return false;
-            }
-            i += 1;
-        }
-        true
+        self.current
+            .overlays
+            .iter()
+            .zip(overlays)
+            .any(|(current, overlay)| {
+                current.name != overlay.name || current.hash() != overlay.hash()
+            })
     }
     fn has_changed(&self, template: &TemplateState) -> bool {
-        self.template_has_changed(template.values())
-            && self.schema_has_changed(template.schema())
-            && self.overlays_have_changed(template.overlays())
+        self.template_has_changed(&template.current)
+            || self.schema_has_changed(&template.current)
+            || self.overlays_have_changed(template.overlays())
+    }
+
+    /// Returns a version of the template, current or previous
+    fn version(&self, version: u32) -> Option<&VersionedTemplate> {
+        std::iter::once(&self.current)
+            .chain(&self.previous_versions)
+            .find(|template| template.version == version)
     }
 
     fn update_template(&mut self, template: TemplateState) {
         let current_version = self.current.version;
         self.previous_versions.push(self.current.clone());
-        self.current = VersionedTemplate {
-            values: template.current.values,
-            overlays: template.current.overlays,
-            version: current_version + 1,
-            schema: template.current.schema,
-            created: chrono::Utc::now().to_string(),
-        }
+        self.current = VersionedTemplate::new(
+            current_version + 1,
+            template.current.values,
+            template.current.overlays,
+            template.current.schema,
+        );
+        self.updated = self.current.created.clone();
     }
 }
 
@@ -118,6 +224,9 @@ struct CometState {
     templates: Vec<TemplateState>,
     created: String,
     updated: String,
+    /// Hashes of the files `init` wrote, by path relative to the project root
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    written: BTreeMap<String, String>,
 }
 
 impl CometState {
@@ -127,6 +236,7 @@ impl CometState {
             templates: Vec::new(),
             created: chrono::Utc::now().to_string(),
             updated: chrono::Utc::now().to_string(),
+            written: BTreeMap::new(),
         }
     }
     fn get_template_mut(&mut self, name: &str) -> Option<&mut TemplateState> {
@@ -140,6 +250,17 @@ impl CometState {
         None
     }
 
+    /// Returns an implementation and the template it implements
+    fn implementation(&self, name: &str) -> Option<(&TemplateState, &Implementation)> {
+        self.templates.iter().find_map(|template| {
+            template
+                .implementations
+                .iter()
+                .find(|implementation| implementation.name == name)
+                .map(|implementation| (template, implementation))
+        })
+    }
+
     fn update_templates(&mut self, templates: Vec<TemplateState>) {
         let mut has_changed = false;
         for template in templates {
@@ -159,59 +280,279 @@ impl CometState {
     }
 }
 
-pub fn sync_state_file(name: &str, path: &str) {
-    let mut state_file = path.to_owned();
-    state_file.push_str("gitcomet.gtcstate");
-    let mut state: CometState;
-    if let Ok(state_str) = fs::read_to_string(state_file.clone()) {
-        state = serde_json::from_str(&state_str).expect("should parse ok");
-    } else {
-        state = CometState::new(name);
-    }
-    let mut templates_folder = path.to_owned();
-    templates_folder.push_str("templates/");
-    let paths = fs::read_dir(templates_folder).unwrap();
+/// Records the templates of a project in its state file, adding a version to every template
+/// that changed since the last sync. Returns the warnings of the template schemas, see
+/// `schema::check`, with the name of their template.
+pub fn sync_state_file(project: &Project) -> Result<Vec<(String, ValidationIssue)>, Error> {
+    let mut state = load_state(project)?.unwrap_or_else(|| CometState::new(project.name()));
     let mut templ_states: Vec<TemplateState> = Vec::new();
-    for path in paths {
-        match path {
-            Ok(path) => {
-                let name = path.file_name();
-                let name_str = name.to_str().expect("should work");
-                let mut base_path = path.path().clone();
-                base_path.push("base.yaml");
-                let base = File::from_yaml(base_path);
-
-                let mut schema_path = path.path().clone();
-                schema_path.push("schema.yaml");
-                let schema = File::from_yaml(schema_path);
-                let mut overlays_folder = path.path().clone();
-                overlays_folder.push("overlays/");
-                let overlays = fs::read_dir(overlays_folder).unwrap();
-                let mut ovrlys: Vec<Overlay> = Vec::new();
-                for overlay in overlays {
-                    match overlay {
-                        Ok(overlay) => {
-                            let name = overlay.file_name();
-                            let name_str = name.to_str().expect("should work");
-                            let contents = File::from_yaml(overlay.path());
-                            let ovrly = Overlay::new(name_str, contents.data().clone());
-                            ovrlys.push(ovrly);
-                        }
-                        _ => todo!(),
-                    }
-                }
-                let tmpl = TemplateState::new(
-                    name_str,
-                    base.data().clone(),
-                    ovrlys,
-                    schema.data().clone(),
-                );
-                templ_states.push(tmpl);
-            }
-            _ => todo!(),
+    let mut warnings = Vec::new();
+    for template in render::template_names(project)? {
+        let template_folder = project.template_dir(&template);
+        let base = StoredDocuments::load(template_file(&template_folder, "base"))?;
+        let schema_path = template_file(&template_folder, "schema");
+        let schema = File::load(&schema_path)?;
+        schema::validator(&schema, &schema_path)?;
+        for warning in schema::check(&schema).warnings {
+            warnings.push((template.clone(), warning));
+        }
+        let overlays_folder = project.overlays_dir(&template);
+        let mut ovrlys: Vec<Overlay> = Vec::new();
+        for name in render::overlay_names(project, &template)? {
+            let contents = StoredDocuments::load(overlays_folder.join(&name))?;
+            ovrlys.push(Overlay::new(&name, contents));
         }
+        ovrlys.sort_by(|a, b| a.name.cmp(&b.name));
+        let tmpl = TemplateState::new(&template, base, ovrlys, schema.data().clone());
+        templ_states.push(tmpl);
     }
     state.update_templates(templ_states);
-    let final_json = serde_json::to_string_pretty(&state).expect("expected to work");
-    fs::write(&state_file, &final_json).expect("should work");
+    save_state(project, &state)?;
+    Ok(warnings)
+}
+
+/// Key of a project file in the hashes of the files `init` wrote
+fn written_key(project: &Project, path: &Path) -> String {
+    let relative = path.strip_prefix(project.root()).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Returns the hashes of the files `init` wrote, by path, empty if there is no state yet
+pub(crate) fn written_hashes(project: &Project) -> Result<BTreeMap<PathBuf, String>, Error> {
+    let written = load_state(project)?.map(|state| state.written);
+    Ok(written
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, hash)| (project.root().join(key), hash))
+        .collect())
+}
+
+/// Records the files `init` wrote, with their content, in the state file
+pub(crate) fn record_written(project: &Project, files: &[(&Path, &str)]) -> Result<(), Error> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    let mut state = load_state(project)?.unwrap_or_else(|| CometState::new(project.name()));
+    for (path, content) in files {
+        state
+            .written
+            .insert(written_key(project, path), text_hash(content));
+    }
+    save_state(project, &state)
+}
+
+/// Reads the state file, `None` if there is none yet
+fn load_state(project: &Project) -> Result<Option<CometState>, Error> {
+    let state_file = project.state_file();
+    let Ok(state_str) = fs::read_to_string(&state_file) else {
+        return Ok(None);
+    };
+    let state = serde_json::from_str(&state_str).map_err(|source| file::Error::Json {
+        path: state_file,
+        source,
+    })?;
+    Ok(Some(state))
+}
+
+/// Reads the state file, which `sync` has to have written first
+fn load_synced_state(project: &Project) -> Result<CometState, Error> {
+    load_state(project)?.ok_or_else(|| Error::State {
+        path: project.state_file(),
+        message: "no state yet, run sync first".to_owned(),
+    })
+}
+
+fn save_state(project: &Project, state: &CometState) -> Result<(), Error> {
+    let state_file = project.state_file();
+    let final_json = serde_json::to_string_pretty(state).expect("state should serialize");
+    let io_error = |source| file::Error::Io {
+        path: state_file.clone(),
+        source,
+    };
+    if let Some(folder) = state_file.parent() {
+        fs::create_dir_all(folder).map_err(io_error)?;
+    }
+    fs::write(&state_file, &final_json).map_err(io_error)?;
+    Ok(())
+}
+
+/// Adds an implementation of a synced template. It is pinned to `version`, the current version
+/// if `None`, and renders the given overlays of that version, by file name with or without
+/// extension, followed by `overlay`. Implementation names are unique across templates.
+pub fn add_implementation(
+    project: &Project,
+    template: &str,
+    name: &str,
+    version: Option<u32>,
+    overlays: &[String],
+    overlay: Value,
+) -> Result<(), Error> {
+    let mut state = load_synced_state(project)?;
+    let state_error = |message: String| Error::State {
+        path: project.state_file(),
+        message,
+    };
+    if state.implementation(name).is_some() {
+        return Err(state_error(format!(
+            "implementation {} already exists",
+            name
+        )));
+    }
+    let template_state = state
+        .get_template_mut(template)
+        .ok_or_else(|| state_error(format!("unknown template {}", template)))?;
+    let version = version.unwrap_or(template_state.current.version);
+    let versioned = template_state
+        .version(version)
+        .ok_or_else(|| state_error(format!("template {} has no version {}", template, version)))?;
+    let mut overlay_names = Vec::new();
+    for overlay in overlays {
+        let found = versioned
+            .overlays
+            .iter()
+            .find(|known| {
+                known.name == *overlay
+                    || Path::new(&known.name).file_stem() == Some(overlay.as_ref())
+            })
+            .ok_or_else(|| {
+                state_error(format!(
+                    "template {} version {} has no overlay {}",
+                    template, version, overlay
+                ))
+            })?;
+        overlay_names.push(found.name.clone());
+    }
+    template_state.implementations.push(Implementation {
+        name: name.to_owned(),
+        version,
+        overlays: overlay_names,
+        overlay,
+        created: chrono::Utc::now().to_string(),
+    });
+    state.updated = chrono::Utc::now().to_string();
+    save_state(project, &state)
+}
+
+/// An implementation rendered with one of the versions of its template
+pub struct RenderedImplementation {
+    pub template: String,
+    pub version: u32,
+    pub documents: Vec<File>,
+    /// Schema of the template version
+    pub schema: Value,
+}
+
+/// Renders an implementation with the base and overlays of the template version it is pinned
+/// to, or of `version` when given, then its inline overlay
+pub fn render_implementation(
+    project: &Project,
+    name: &str,
+    version: Option<u32>,
+    strategy: &MergeStrategy,
+) -> Result<RenderedImplementation, Error> {
+    let state = load_synced_state(project)?;
+    let state_error = |message: String| Error::State {
+        path: project.state_file(),
+        message,
+    };
+    let (template, implementation) = state
+        .implementation(name)
+        .ok_or_else(|| state_error(format!("unknown implementation {}", name)))?;
+    let version = version.unwrap_or(implementation.version);
+    let versioned = template.version(version).ok_or_else(|| {
+        state_error(format!(
+            "template {} has no version {}",
+            template.name, version
+        ))
+    })?;
+    let mut documents = versioned.values.files();
+    for overlay_name in &implementation.overlays {
+        let overlay = versioned
+            .overlays
+            .iter()
+            .find(|overlay| overlay.name == *overlay_name)
+            .ok_or_else(|| {
+                state_error(format!(
+                    "template {} version {} has no overlay {}",
+                    template.name, version, overlay_name
+                ))
+            })?;
+        let path = Path::new(&overlay.name);
+        render::merge_documents(&mut documents, overlay.values.files(), strategy, path)?;
+    }
+    if !implementation.overlay.is_null() {
+        let inline = vec![File::new(implementation.overlay.clone())];
+        render::merge_documents(&mut documents, inline, strategy, &project.state_file())?;
+    }
+    Ok(RenderedImplementation {
+        template: template.name.clone(),
+        version,
+        documents,
+        schema: versioned.schema.clone(),
+    })
+}
+
+/// Pins an implementation to another version of its template
+pub fn upgrade_implementation(project: &Project, name: &str, version: u32) -> Result<(), Error> {
+    let mut state = load_synced_state(project)?;
+    let implementation = state
+        .templates
+        .iter_mut()
+        .flat_map(|template| template.implementations.iter_mut())
+        .find(|implementation| implementation.name == name)
+        .ok_or_else(|| Error::State {
+            path: project.state_file(),
+            message: format!("unknown implementation {}", name),
+        })?;
+    implementation.version = version;
+    state.updated = chrono::Utc::now().to_string();
+    save_state(project, &state)
+}
+
+/// Returns the current version of the template an implementation belongs to
+pub fn current_version(project: &Project, name: &str) -> Result<u32, Error> {
+    let state = load_synced_state(project)?;
+    let (template, _) = state.implementation(name).ok_or_else(|| Error::State {
+        path: project.state_file(),
+        message: format!("unknown implementation {}", name),
+    })?;
+    Ok(template.current.version)
+}
+
+/// Lists the implementations of every template, with the name of their template
+pub fn list_implementations(project: &Project) -> Result<Vec<(String, Implementation)>, Error> {
+    let state = load_synced_state(project)?;
+    Ok(state
+        .templates
+        .into_iter()
+        .flat_map(|template| {
+            let name = template.name;
+            template
+                .implementations
+                .into_iter()
+                .map(move |implementation| (name.clone(), implementation))
+        })
+        .collect())
+}
+
+pub fn remove_implementation(project: &Project, name: &str) -> Result<(), Error> {
+    let mut state = load_synced_state(project)?;
+    let template = state
+        .templates
+        .iter_mut()
+        .find(|template| template.implementations.iter().any(|i| i.name == name))
+        .ok_or_else(|| Error::State {
+            path: project.state_file(),
+            message: format!("unknown implementation {}", name),
+        })?;
+    template.implementations.retain(|i| i.name != name);
+    state.updated = chrono::Utc::now().to_string();
+    save_state(project, &state)
+}
+
+/// Returns the path of a template file in any supported format, defaulting to yaml so a missing
+/// file is reported with the conventional name.
+pub fn template_file(template_folder: &Path, stem: &str) -> PathBuf {
+    file::find_file(template_folder, stem)
+        .unwrap_or_else(|| template_folder.join(format!("{}.yaml", stem)))
 }
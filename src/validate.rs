@@ -0,0 +1,130 @@
+use crate::config::Project;
+use crate::error::Error;
+use crate::file::LoadOptions;
+use crate::merge::MergeStrategy;
+use crate::render;
+use crate::report::ValidationReport;
+use crate::schema;
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Outcome of rendering a template with one of its overlays and validating the result
+pub struct TemplateCheck {
+    pub template: String,
+    /// Overlay file name, `None` when the base is checked alone or the schema is unusable
+    pub overlay: Option<String>,
+    pub outcome: Result<ValidationReport, Error>,
+}
+
+impl TemplateCheck {
+    pub fn is_valid(&self) -> bool {
+        self.outcome.as_ref().is_ok_and(ValidationReport::is_valid)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut check = json!({
+            "template": self.template,
+            "overlay": self.overlay,
+            "valid": self.is_valid(),
+        });
+        match &self.outcome {
+            Ok(report) => {
+                check["errors"] = json!(report.errors);
+                if !report.warnings.is_empty() {
+                    check["warnings"] = json!(report.warnings);
+                }
+            }
+            Err(error) => check["error"] = json!(error.to_string()),
+        }
+        check
+    }
+}
+
+impl fmt::Display for TemplateCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.template)?;
+        if let Some(overlay) = &self.overlay {
+            write!(f, " + {}", overlay)?;
+        }
+        match &self.outcome {
+            Ok(report) => {
+                if report.is_valid() {
+                    write!(f, ": ok")?;
+                } else {
+                    write!(f, ": {} error(s)", report.errors.len())?;
+                }
+                if report.warnings.is_empty() {
+                    return writeln!(f);
+                }
+                writeln!(f, ", {} warning(s)", report.warnings.len())?;
+                for line in report.to_string().lines() {
+                    if line.is_empty() {
+                        writeln!(f)?;
+                    } else {
+                        writeln!(f, "  {}", line)?;
+                    }
+                }
+                Ok(())
+            }
+            Err(error) => writeln!(f, ": {}", error),
+        }
+    }
+}
+
+/// Renders every template of a project with each of its overlays, or alone if it has none, and
+/// validates the result against the template's schema. With `defaults`, the defaults of the
+/// schema are applied before validating. Warnings about the schema itself, e.g. unknown
+/// keywords, are added to each report.
+pub fn check_templates(
+    project: &Project,
+    strategy: &MergeStrategy,
+    options: &LoadOptions,
+    defaults: bool,
+) -> Result<Vec<TemplateCheck>, Error> {
+    let mut checks = Vec::new();
+    for template in render::template_names(project)? {
+        let combinations = match render::overlay_combinations(project, &template) {
+            Ok(combinations) => combinations,
+            Err(error) => {
+                checks.push(TemplateCheck {
+                    template,
+                    overlay: None,
+                    outcome: Err(error),
+                });
+                continue;
+            }
+        };
+        let (schema, validator) = match render::template_validator(project, &template) {
+            Ok(compiled) => compiled,
+            Err(error) => {
+                checks.push(TemplateCheck {
+                    template,
+                    overlay: None,
+                    outcome: Err(error),
+                });
+                continue;
+            }
+        };
+        let warnings = schema::check(&schema).warnings;
+        for overlay in combinations {
+            let outcome =
+                render::render_template(project, &template, overlay.as_slice(), strategy, options)
+                    .map(|mut rendered| {
+                        if defaults {
+                            for document in rendered.iter_mut() {
+                                document.apply_defaults(schema.data());
+                            }
+                        }
+                        let mut report = ValidationReport::new(&validator, &rendered);
+                        report.warnings = warnings.clone();
+                        report
+                    });
+            checks.push(TemplateCheck {
+                template: template.clone(),
+                overlay,
+                outcome,
+            });
+        }
+    }
+    Ok(checks)
+}
@@ -0,0 +1,446 @@
+use crate::file::Error;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use yaml_rust2::parser::{MarkedEventReceiver, Parser, Tag};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::{Event, Yaml, YamlEmitter};
+
+/// Key holding the tag of a tagged value, e.g. `!Ref` or `!!binary`
+pub const TAG: &str = "$tag";
+/// Key holding the value of a tagged value
+pub const TAG_VALUE: &str = "$value";
+
+/// Handle the parser expands `!!` to
+const CORE_HANDLE: &str = "tag:yaml.org,2002:";
+/// Tag of special floats, which have no json number equivalent
+const FLOAT_TAG: &str = "!!float";
+
+/// Builds a tagged value, `{"$tag": tag, "$value": value}`
+pub fn tagged(tag: &str, value: Value) -> Value {
+    let mut obj = Map::new();
+    obj.insert(TAG.to_owned(), Value::String(tag.to_owned()));
+    obj.insert(TAG_VALUE.to_owned(), value);
+    Value::Object(obj)
+}
+
+/// Returns the tag and value of a tagged value
+pub fn as_tagged(value: &Value) -> Option<(&str, &Value)> {
+    let obj = value.as_object()?;
+    if obj.len() != 2 {
+        return None;
+    }
+    Some((obj.get(TAG)?.as_str()?, obj.get(TAG_VALUE)?))
+}
+
+/// Reads every document of a yaml stream without losing data: non-string keys are kept as
+/// their source text, anchors, aliases and `<<` merge keys are expanded, custom tags and special
+/// floats become tagged values, and anything that can't be represented is an error.
+pub(crate) fn load_lossless(content: &str, path: &Path) -> Result<Vec<Value>, Error> {
+    let mut receiver = LosslessReceiver::default();
+    let mut parser = Parser::new_from_str(content);
+    parser
+        .load(&mut receiver, true)
+        .map_err(|error| yaml_error(path, error.marker(), error.info().to_owned()))?;
+    if let Some((mark, error)) = receiver.error {
+        return Err(match error {
+            LosslessError::NonStringKey(key) => Error::NonStringKey {
+                path: Some(path.to_path_buf()),
+                key,
+            },
+            LosslessError::Invalid(message) => yaml_error(path, &mark, message),
+        });
+    }
+    Ok(receiver.documents)
+}
+
+fn yaml_error(path: &Path, mark: &Marker, message: String) -> Error {
+    Error::Yaml {
+        path: path.to_path_buf(),
+        line: mark.line(),
+        column: mark.col() + 1,
+        message,
+    }
+}
+
+enum LosslessError {
+    NonStringKey(String),
+    Invalid(String),
+}
+
+enum Frame {
+    Sequence {
+        items: Vec<Value>,
+        tag: Option<String>,
+        anchor: usize,
+    },
+    Mapping {
+        entries: Map<String, Value>,
+        key: Option<String>,
+        /// Mappings merged in with `<<`, applied once the mapping ends
+        merges: Vec<Map<String, Value>>,
+        tag: Option<String>,
+        anchor: usize,
+    },
+}
+
+#[derive(Default)]
+struct LosslessReceiver {
+    documents: Vec<Value>,
+    stack: Vec<Frame>,
+    anchors: HashMap<usize, Value>,
+    root: Option<Value>,
+    error: Option<(Marker, LosslessError)>,
+}
+
+impl MarkedEventReceiver for LosslessReceiver {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(error) = self.handle(event) {
+            self.error = Some((mark, error));
+        }
+    }
+}
+
+impl LosslessReceiver {
+    fn handle(&mut self, event: Event) -> Result<(), LosslessError> {
+        match event {
+            Event::DocumentEnd => {
+                let document = self.root.take().unwrap_or(Value::Null);
+                self.documents.push(document);
+            }
+            Event::SequenceStart(anchor, tag) => self.stack.push(Frame::Sequence {
+                items: Vec::new(),
+                tag: custom_tag(tag.as_ref(), "seq"),
+                anchor,
+            }),
+            Event::MappingStart(anchor, tag) => self.stack.push(Frame::Mapping {
+                entries: Map::new(),
+                key: None,
+                merges: Vec::new(),
+                tag: custom_tag(tag.as_ref(), "map"),
+                anchor,
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let (value, tag, anchor) = match self.stack.pop() {
+                    Some(Frame::Sequence { items, tag, anchor }) => {
+                        (Value::Array(items), tag, anchor)
+                    }
+                    Some(Frame::Mapping {
+                        mut entries,
+                        merges,
+                        tag,
+                        anchor,
+                        ..
+                    }) => {
+                        for merged in merges {
+                            for (key, value) in merged {
+                                entries.entry(key).or_insert(value);
+                            }
+                        }
+                        (Value::Object(entries), tag, anchor)
+                    }
+                    None => return Ok(()),
+                };
+                let value = match tag {
+                    Some(tag) => tagged(&tag, value),
+                    None => value,
+                };
+                self.insert(value, anchor, None)?;
+            }
+            Event::Scalar(text, style, anchor, tag) => {
+                let raw_key = (style == TScalarStyle::Plain).then(|| text.clone());
+                let value = scalar(text, style, tag.as_ref())?;
+                self.insert(value, anchor, raw_key)?;
+            }
+            Event::Alias(id) => {
+                let value = self
+                    .anchors
+                    .get(&id)
+                    .cloned()
+                    .ok_or_else(|| LosslessError::Invalid("unknown anchor".to_owned()))?;
+                self.insert(value, 0, None)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Adds a finished node to its parent. `raw_key` is the source text of plain scalars, which
+    /// is used as is when the node is a mapping key.
+    fn insert(
+        &mut self,
+        value: Value,
+        anchor: usize,
+        raw_key: Option<String>,
+    ) -> Result<(), LosslessError> {
+        if anchor > 0 {
+            self.anchors.insert(anchor, value.clone());
+        }
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(Frame::Sequence { items, .. }) => items.push(value),
+            Some(Frame::Mapping {
+                entries,
+                key,
+                merges,
+                ..
+            }) => match key.take() {
+                None => *key = Some(key_string(value, raw_key)?),
+                Some(key) if key == "<<" => match value {
+                    Value::Object(merged) => merges.push(merged),
+                    Value::Array(items) => {
+                        for item in items {
+                            match item {
+                                Value::Object(merged) => merges.push(merged),
+                                _ => {
+                                    return Err(LosslessError::Invalid(
+                                        "merge keys only accept mappings".to_owned(),
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                    value => {
+                        entries.insert(key, value);
+                    }
+                },
+                Some(key) => {
+                    if entries.insert(key.clone(), value).is_some() {
+                        return Err(LosslessError::Invalid(format!(
+                            "duplicated key {} in mapping",
+                            key
+                        )));
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Returns the tag of a node unless it is the core tag of its kind, e.g. `!!map` for mappings
+fn custom_tag(tag: Option<&Tag>, core: &str) -> Option<String> {
+    let tag = tag?;
+    if tag.handle == CORE_HANDLE {
+        if tag.suffix == core {
+            return None;
+        }
+        return Some(format!("!!{}", tag.suffix));
+    }
+    Some(format!("{}{}", tag.handle, tag.suffix))
+}
+
+fn scalar(text: String, style: TScalarStyle, tag: Option<&Tag>) -> Result<Value, LosslessError> {
+    let Some(tag) = tag else {
+        if style != TScalarStyle::Plain {
+            return Ok(Value::String(text));
+        }
+        return plain_scalar(&text, Yaml::from_str(&text));
+    };
+    if tag.handle != CORE_HANDLE {
+        return Ok(tagged(
+            &format!("{}{}", tag.handle, tag.suffix),
+            Value::String(text),
+        ));
+    }
+    let invalid = || LosslessError::Invalid(format!("invalid !!{} value {}", tag.suffix, text));
+    match tag.suffix.as_str() {
+        "str" => Ok(Value::String(text)),
+        "int" => text
+            .parse::<i64>()
+            .map(|int| Value::Number(int.into()))
+            .map_err(|_| invalid()),
+        "float" => plain_scalar(&text, Yaml::Real(text.clone())).and_then(|value| {
+            if value.is_number() || as_tagged(&value).is_some() {
+                Ok(value)
+            } else {
+                Err(invalid())
+            }
+        }),
+        "bool" => text.parse::<bool>().map(Value::Bool).map_err(|_| invalid()),
+        "null" => match text.as_str() {
+            "~" | "null" | "" => Ok(Value::Null),
+            _ => Err(invalid()),
+        },
+        suffix => Ok(tagged(&format!("!!{}", suffix), Value::String(text))),
+    }
+}
+
+fn plain_scalar(text: &str, yaml: Yaml) -> Result<Value, LosslessError> {
+    match yaml {
+        Yaml::Null => Ok(Value::Null),
+        Yaml::Boolean(b) => Ok(Value::Bool(b)),
+        Yaml::Integer(i) => Ok(Value::Number(i.into())),
+        Yaml::Real(real) => match real.parse::<f64>().ok().and_then(Number::from_f64) {
+            Some(number) => Ok(Value::Number(number)),
+            None if is_special_float(&real) => Ok(tagged(FLOAT_TAG, Value::String(real))),
+            // e.g. `nan`, which isn't a yaml float
+            None => Ok(Value::String(text.to_owned())),
+        },
+        _ => Ok(Value::String(text.to_owned())),
+    }
+}
+
+fn is_special_float(text: &str) -> bool {
+    matches!(
+        text.trim_start_matches(['+', '-']),
+        ".inf" | ".Inf" | ".INF" | ".nan" | ".NaN" | ".NAN"
+    )
+}
+
+fn key_string(value: Value, raw_key: Option<String>) -> Result<String, LosslessError> {
+    match value {
+        Value::String(key) => Ok(key),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {
+            Ok(raw_key.unwrap_or_else(|| value.to_string()))
+        }
+        value => match as_tagged(&value) {
+            Some((_, Value::String(key))) => Ok(key.clone()),
+            _ => Err(LosslessError::NonStringKey(value.to_string())),
+        },
+    }
+}
+
+/// Writes a document as block style yaml, laid out like `YamlEmitter`, which can't write tags,
+/// does. Tagged values, see `tagged`, are written with their tag, except special floats, which
+/// are valid plain yaml and only need a tag in json.
+pub(crate) fn emit(document: &Value) -> String {
+    let mut emitter = Emitter {
+        out: String::from("---"),
+        level: -1,
+    };
+    match as_tagged(document) {
+        Some((tag, inner)) if is_collection(inner) => {
+            emitter.out.push(' ');
+            emitter.out.push_str(tag);
+            emitter.out.push('\n');
+            emitter.node(inner);
+        }
+        _ => {
+            emitter.out.push('\n');
+            emitter.node(document);
+        }
+    }
+    emitter.out
+}
+
+/// Follows `YamlEmitter`, with its default compact notation
+struct Emitter {
+    out: String,
+    level: isize,
+}
+
+impl Emitter {
+    fn indent(&mut self) {
+        for _ in 0..self.level.max(0) {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn node(&mut self, value: &Value) {
+        if let Some((tag, inner)) = as_tagged(value) {
+            match (tag, inner) {
+                (FLOAT_TAG, Value::String(float)) if is_special_float(float) => {
+                    self.out.push_str(float);
+                }
+                _ => {
+                    self.out.push_str(tag);
+                    self.out.push(' ');
+                    self.node(inner);
+                }
+            }
+            return;
+        }
+        match value {
+            Value::Array(items) => self.array(items),
+            Value::Object(entries) => self.object(entries),
+            Value::String(str) => self.out.push_str(&emit_scalar(&Yaml::String(str.clone()))),
+            Value::Bool(b) => self.out.push_str(if *b { "true" } else { "false" }),
+            Value::Number(number) => self.out.push_str(&number.to_string()),
+            Value::Null => self.out.push('~'),
+        }
+    }
+
+    fn array(&mut self, items: &[Value]) {
+        if items.is_empty() {
+            self.out.push_str("[]");
+            return;
+        }
+        self.level += 1;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.out.push('\n');
+                self.indent();
+            }
+            self.out.push('-');
+            self.value(true, item);
+        }
+        self.level -= 1;
+    }
+
+    fn object(&mut self, entries: &Map<String, Value>) {
+        if entries.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.level += 1;
+        for (i, (key, value)) in entries.iter().enumerate() {
+            if i > 0 {
+                self.out.push('\n');
+                self.indent();
+            }
+            // keys are strings, quoted when they would read as another type
+            self.out.push_str(&emit_scalar(&Yaml::String(key.clone())));
+            self.out.push(':');
+            self.value(false, value);
+        }
+        self.level -= 1;
+    }
+
+    /// Writes an array item, `inline`, or a mapping value, on the line of its `-` or key when
+    /// possible. Tags of collections go there, the collection starting on the next line.
+    fn value(&mut self, inline: bool, value: &Value) {
+        let (tag, collection) = match as_tagged(value) {
+            Some((tag, inner)) if is_collection(inner) => (Some(tag), inner),
+            None if value.is_array() || value.is_object() => (None, value),
+            // scalars, tagged or not, and tagged empty collections
+            _ => {
+                self.out.push(' ');
+                return self.node(value);
+            }
+        };
+        if let Some(tag) = tag {
+            self.out.push(' ');
+            self.out.push_str(tag);
+        }
+        if (inline && tag.is_none()) || !is_collection(collection) {
+            self.out.push(' ');
+        } else {
+            self.out.push('\n');
+            self.level += 1;
+            self.indent();
+            self.level -= 1;
+        }
+        self.node(collection);
+    }
+}
+
+/// Whether a value is a non-empty array or object, which a tag can't share a line with
+fn is_collection(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(entries) => !entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Writes a scalar as `YamlEmitter` does, quoting strings when needed
+fn emit_scalar(yaml: &Yaml) -> String {
+    let mut out = String::new();
+    // emitting a scalar into a String can't fail
+    let _ = YamlEmitter::new(&mut out).dump(yaml);
+    out.trim_start_matches("---").trim_start().to_owned()
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::Sandbox;
+use overl::catalogue::CATALOGUE;
+use overl::config::Config;
+use overl::error::Error;
+use overl::file::{File, LoadOptions};
+use overl::merge::MergeStrategy;
+use overl::{init, schema, validate};
+
+#[test]
+fn catalogue_templates_validate_against_their_schemas() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    let names: Vec<String> = CATALOGUE.iter().map(|t| t.name.to_owned()).collect();
+    let plan = init::plan(root, Config::default(), &names, false).unwrap();
+    plan.apply().unwrap();
+    let project = plan.project;
+
+    for name in &names {
+        let schema = File::load(project.template_dir(name).join("schema.yaml")).unwrap();
+        let report = schema::check(&schema);
+        assert!(report.is_valid(), "{} schema:\n{}", name, report);
+    }
+    let checks = validate::check_templates(
+        &project,
+        &MergeStrategy::default(),
+        &LoadOptions::default(),
+        false,
+    )
+    .unwrap();
+    assert_eq!(checks.len(), CATALOGUE.len());
+    for check in checks {
+        assert!(check.is_valid(), "{}", check);
+    }
+
+    let deployment = init::catalogue_template("deployment").unwrap();
+    assert!(matches!(
+        init::add_template(&project, deployment, "deployment"),
+        Err(Error::AlreadyExists { path }) if path == project.template_dir("deployment")
+    ));
+    init::add_template(&project, deployment, "worker").unwrap();
+    assert!(project.overlays_dir("worker").join("prod.yaml").is_file());
+    assert!(init::catalogue_template("terraform").is_err());
+}
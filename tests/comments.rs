@@ -0,0 +1,73 @@
+mod common;
+
+use common::Sandbox;
+use overl::file::File;
+use overl::merge::{ArrayMerge, MergeStrategy};
+
+#[test]
+fn base_comments_survive_merge() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write(
+        "base.yaml",
+        "# the application\nkind: Application\nmetadata:\n  namespace: argocd # where argo lives\n  finalizers:\n    # cascade deletes\n    - resources-finalizer\n",
+    );
+    let overlay = sandbox.write("overlay.yaml", "metadata:\n  name: guestbook # app name\n");
+    let mut file = File::load_documents_with_comments(&base).unwrap().remove(0);
+    let overlay_file = File::load_documents_with_comments(&overlay)
+        .unwrap()
+        .remove(0);
+    file.merge(overlay_file);
+    assert_eq!(
+        file.to_yaml_string(),
+        "---\n# the application\nkind: Application\nmetadata:\n  namespace: argocd # where argo lives\n  finalizers:\n    # cascade deletes\n    - resources-finalizer\n  name: guestbook # app name"
+    );
+}
+
+#[test]
+fn overlay_comments_take_precedence() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write(
+        "precedence-base.yaml",
+        "# base comment\nreplicas: 1 # one\nname: web\n",
+    );
+    let overlay = sandbox.write(
+        "precedence-overlay.yaml",
+        "# scaled up for prod\nreplicas: 3\n",
+    );
+    let mut file = File::load_documents_with_comments(&base).unwrap().remove(0);
+    file.merge(
+        File::load_documents_with_comments(&overlay)
+            .unwrap()
+            .remove(0),
+    );
+    assert_eq!(
+        file.to_yaml_string(),
+        "---\n# scaled up for prod\nreplicas: 3\nname: web"
+    );
+}
+
+#[test]
+fn item_comments_follow_their_items() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write(
+        "items-base.yaml",
+        "args:\n  - --verbose # noisy\n  - --port=80\nparams:\n  # first\n  - name: a\n    value: 1\n  # second\n  - name: b # bee\n    value: 2\n  - name: c\n    value: 3\n",
+    );
+    let overlay = sandbox.write(
+        "items-overlay.yaml",
+        "args:\n  # prepended\n  - --debug\nparams:\n  - name: a\n    $patch: delete\n  - name: c # sea\n    value: 30\n  - name: d # new\n    value: 4\n",
+    );
+    let strategy = MergeStrategy::new(ArrayMerge::Prepend)
+        .with_path("/params", ArrayMerge::ByKey("name".to_owned()));
+    let mut file = File::load_documents_with_comments(&base).unwrap().remove(0);
+    file.merge_with(
+        File::load_documents_with_comments(&overlay)
+            .unwrap()
+            .remove(0),
+        &strategy,
+    );
+    assert_eq!(
+        file.to_yaml_string(),
+        "---\nargs:\n  # prepended\n  - \"--debug\"\n  - \"--verbose\" # noisy\n  - \"--port=80\"\nparams:\n  # second\n  - name: b # bee\n    value: 2\n  - name: c # sea\n    value: 30\n  - name: d # new\n    value: 4"
+    );
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::Sandbox;
+use overl::config::{Config, Project};
+use overl::init;
+use std::fs;
+
+#[test]
+fn discover_walks_up_to_the_config() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    fs::write(
+        root.join("overl.toml"),
+        "name = \"shop\"\ntemplates = \"tmpl\"\noverlays = \"envs\"\n",
+    )
+    .unwrap();
+    let nested = root.join("apps").join("web");
+    fs::create_dir_all(&nested).unwrap();
+
+    let project = Project::discover(&nested).unwrap();
+    assert_eq!(project.name(), "shop");
+    assert!(project.overlays_dir("web").ends_with("tmpl/web/envs"));
+    assert_eq!(
+        project
+            .templates_dir()
+            .parent()
+            .unwrap()
+            .canonicalize()
+            .unwrap(),
+        root.canonicalize().unwrap()
+    );
+    assert_eq!(project.config().state, Config::default().state);
+}
+
+#[test]
+fn init_writes_a_discoverable_project() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    let config = Config {
+        name: "shop".to_owned(),
+        ..Config::default()
+    };
+    init::plan(root, config.clone(), &[], false)
+        .unwrap()
+        .apply()
+        .unwrap();
+
+    let project = Project::discover(root).unwrap();
+    assert_eq!(project.config(), &config);
+    assert!(project
+        .overlays_dir("argocd")
+        .join("helm_app.yaml")
+        .is_file());
+}
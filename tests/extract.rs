@@ -0,0 +1,91 @@
+mod common;
+
+use common::Sandbox;
+use overl::config::{Config, Project};
+use overl::error::Error;
+use overl::extract;
+use overl::file::{self, File};
+use serde_json::json;
+use std::fs;
+
+#[test]
+fn common_keeps_what_every_value_shares() {
+    let guestbook = json!({
+        "kind": "Application",
+        "metadata": {"name": "guestbook", "namespace": "argocd"},
+        "spec": {"project": "default", "syncOptions": ["Prune"]}
+    });
+    let shop = json!({
+        "kind": "Application",
+        "metadata": {"name": "shop", "namespace": "argocd"},
+        "spec": {"project": "default", "syncOptions": ["Prune", "Replace"]}
+    });
+    let base = extract::common(&[&guestbook, &shop]).unwrap();
+    assert_eq!(
+        base,
+        json!({
+            "kind": "Application",
+            "metadata": {"namespace": "argocd"},
+            "spec": {"project": "default"}
+        })
+    );
+    assert_eq!(
+        extract::difference(&base, &shop),
+        json!({"metadata": {"name": "shop"}, "spec": {"syncOptions": ["Prune", "Replace"]}})
+    );
+    assert_eq!(extract::common(&[&json!(1), &json!("1")]), None);
+}
+
+#[test]
+fn extract_writes_a_template_rendering_each_file() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    let apps = [
+        (
+            "guestbook.yaml",
+            "kind: Application\nmetadata:\n  name: guestbook\n",
+        ),
+        ("shop.yaml", "kind: Application\nmetadata:\n  name: shop\n"),
+    ];
+    let mut files = Vec::new();
+    for (name, content) in apps {
+        fs::write(root.join(name), content).unwrap();
+        files.push(root.join(name));
+    }
+    let project = Project::new(root, Config::default());
+
+    extract::extract_template(&project, "apps", &files).unwrap();
+    let template = project.template_dir("apps");
+    assert_eq!(
+        File::load(template.join("base.yaml")).unwrap().data(),
+        &json!({"kind": "Application", "metadata": {}})
+    );
+    assert_eq!(
+        File::load(project.overlays_dir("apps").join("shop.yaml"))
+            .unwrap()
+            .data(),
+        &json!({"metadata": {"name": "shop"}})
+    );
+    assert!(template.join("schema.yaml").is_file());
+
+    fs::write(
+        root.join("patched.yaml"),
+        "kind: Application\n$patch: delete\n",
+    )
+    .unwrap();
+    files.push(root.join("patched.yaml"));
+    assert!(extract::extract_template(&project, "patched", &files).is_err());
+    assert!(!project.template_dir("patched").exists());
+
+    fs::write(
+        root.join("stream.yaml"),
+        "kind: Application\n---\nkind: Application\n",
+    )
+    .unwrap();
+    let stream = [root.join("guestbook.yaml"), root.join("stream.yaml")];
+    assert!(matches!(
+        extract::extract_template(&project, "stream", &stream),
+        Err(Error::File(file::Error::MultipleDocuments { count: 2, .. }))
+    ));
+    assert!(!project.template_dir("stream").exists());
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::Sandbox;
+use overl::file::{self, Error, File, Format};
+use overl::state;
+use serde_json::json;
+use std::fs;
+use std::io;
+
+#[test]
+fn load_reads_the_format_of_the_extension() {
+    let sandbox = Sandbox::new();
+    let expected = json!({"name": "web", "ports": [80, 443]});
+    for (name, content) in [
+        ("app.yaml", "name: web\nports: [80, 443]\n"),
+        ("app.yml", "name: web\nports:\n  - 80\n  - 443\n"),
+        ("app.json", r#"{"name": "web", "ports": [80, 443]}"#),
+        ("app.toml", "name = \"web\"\nports = [80, 443]\n"),
+    ] {
+        let path = sandbox.write(name, content);
+        assert_eq!(File::load(&path).unwrap().data(), &expected, "{}", name);
+    }
+    // the extension wins over the content
+    let path = sandbox.write("yaml.json", "name: web\n");
+    assert!(File::load(&path).is_err());
+}
+
+#[test]
+fn load_sniffs_files_without_a_known_extension() {
+    assert_eq!(Format::sniff(r#"{"name": "web"}"#), Format::Json);
+    assert_eq!(Format::sniff("name = \"web\"\n"), Format::Toml);
+    assert_eq!(Format::sniff("name: web\n"), Format::Yaml);
+    assert_eq!(Format::sniff(""), Format::Yaml);
+
+    let sandbox = Sandbox::new();
+    for (name, content) in [
+        ("json", r#"{"name": "web"}"#),
+        ("Tomlfile", "name = \"web\"\n"),
+        ("values.txt", "name: web\n"),
+    ] {
+        let path = sandbox.write(name, content);
+        assert_eq!(
+            File::load(&path).unwrap().data(),
+            &json!({"name": "web"}),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn save_writes_the_format_of_the_extension() {
+    let sandbox = Sandbox::new();
+    let file = File::new(json!({"name": "web", "ports": [80, 443]}));
+    for name in ["app.yaml", "app.json", "app.toml", "app.conf"] {
+        let path = sandbox.path().join(name);
+        file.save(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let format = Format::from_path(&path).unwrap_or(Format::Yaml);
+        assert_eq!(Format::sniff(&content), format, "{}", name);
+        assert!(
+            content.ends_with('\n') && !content.ends_with("\n\n"),
+            "{}",
+            name
+        );
+        assert_eq!(File::load(&path).unwrap().data(), file.data(), "{}", name);
+    }
+
+    let documents = [File::new(json!({"a": 1})), File::new(json!({"b": 2}))];
+    let path = sandbox.path().join("stream.yaml");
+    file::save_documents(&documents, &path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "---\na: 1\n---\nb: 2\n");
+    let loaded = File::load_documents(&path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[1].data(), &json!({"b": 2}));
+    assert!(file::save_documents(&documents, sandbox.path().join("stream.json")).is_err());
+}
+
+#[test]
+fn template_file_finds_any_supported_format() {
+    let sandbox = Sandbox::new();
+    let folder = sandbox.path();
+    assert_eq!(
+        state::template_file(folder, "base"),
+        folder.join("base.yaml")
+    );
+    sandbox.write("base.json", "{}");
+    assert_eq!(
+        state::template_file(folder, "base"),
+        folder.join("base.json")
+    );
+    sandbox.write("schema.toml", "type = \"object\"\n");
+    assert_eq!(
+        state::template_file(folder, "schema"),
+        folder.join("schema.toml")
+    );
+}
+
+#[test]
+fn load_reports_what_failed_and_where() {
+    let sandbox = Sandbox::new();
+    let syntax = sandbox.write("syntax.yaml", "name: web\nports: [80, 443}\nreplicas: 2\n");
+    assert!(matches!(
+        File::load(&syntax),
+        Err(Error::Yaml { path, line: 2, column: 16, .. }) if path == syntax
+    ));
+    let infinite = sandbox.write("infinite.yaml", "ratio: .inf\n");
+    assert!(matches!(
+        File::load(&infinite),
+        Err(Error::UnrepresentableNumber { path: Some(path), value }) if path == infinite && value == ".inf"
+    ));
+    let keys = sandbox.write("keys.yaml", "200: ok\n");
+    assert!(matches!(
+        File::load(&keys),
+        Err(Error::NonStringKey { path: Some(path), .. }) if path == keys
+    ));
+    let missing = sandbox.path().join("missing.yaml");
+    assert!(matches!(
+        File::load(&missing),
+        Err(Error::Io { path, source }) if path == missing && source.kind() == io::ErrorKind::NotFound
+    ));
+}
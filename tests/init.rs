@@ -0,0 +1,72 @@
+mod common;
+
+use common::Sandbox;
+use overl::config::Config;
+use overl::error::Error;
+use overl::init::{self, Action};
+use std::fs;
+
+#[test]
+fn init_only_creates_missing_pieces() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    let templates = ["compose".to_owned()];
+    let plan = init::plan(root, Config::default(), &templates, false).unwrap();
+    assert!(plan
+        .pieces
+        .iter()
+        .all(|piece| piece.action == Action::Create));
+    plan.apply().unwrap();
+
+    let rerun = init::plan(root, Config::default(), &templates, false).unwrap();
+    assert_eq!(rerun.changes().count(), 0);
+
+    let base = rerun.project.template_dir("compose").join("base.yaml");
+    let overlay = rerun.project.overlays_dir("compose").join("dev.yaml");
+    fs::write(&base, "services: {}\n").unwrap();
+    fs::remove_file(&overlay).unwrap();
+    let plan = init::plan(root, Config::default(), &templates, false).unwrap();
+    let conflicts: Vec<_> = plan.conflicts().map(|piece| &piece.path).collect();
+    assert_eq!(conflicts, [&base]);
+    let changes: Vec<_> = plan.changes().map(|piece| &piece.path).collect();
+    assert_eq!(changes, [&overlay]);
+    assert!(plan.apply().is_err());
+    assert_eq!(fs::read_to_string(&base).unwrap(), "services: {}\n");
+    assert!(!overlay.exists());
+
+    let plan = init::plan(root, Config::default(), &templates, true).unwrap();
+    let changes: Vec<_> = plan.changes().map(|piece| &piece.path).collect();
+    assert_eq!(changes, [&base, &overlay]);
+    plan.apply().unwrap();
+    assert_ne!(fs::read_to_string(&base).unwrap(), "services: {}\n");
+    assert!(overlay.is_file());
+}
+
+#[test]
+fn init_only_overwrites_files_it_did_not_write_when_forced() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    init::plan(root, Config::default(), &["compose".to_owned()], false)
+        .unwrap()
+        .apply()
+        .unwrap();
+
+    let templates = ["helm-values".to_owned()];
+    let plan = init::plan(root, Config::default(), &templates, false).unwrap();
+    let base = plan.project.template_dir("helm-values").join("base.yaml");
+    fs::create_dir_all(base.parent().unwrap()).unwrap();
+    fs::write(&base, "replicaCount: 2\n").unwrap();
+    let plan = init::plan(root, Config::default(), &templates, false).unwrap();
+    let conflicts: Vec<_> = plan.conflicts().map(|piece| &piece.path).collect();
+    assert_eq!(conflicts, [&base]);
+    assert!(matches!(plan.apply(), Err(Error::Modified { paths }) if paths == [base.clone()]));
+    assert_eq!(fs::read_to_string(&base).unwrap(), "replicaCount: 2\n");
+
+    init::plan(root, Config::default(), &templates, true)
+        .unwrap()
+        .apply()
+        .unwrap();
+    assert_ne!(fs::read_to_string(&base).unwrap(), "replicaCount: 2\n");
+    let rerun = init::plan(root, Config::default(), &templates, false).unwrap();
+    assert_eq!(rerun.conflicts().count(), 0);
+}
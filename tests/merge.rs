@@ -0,0 +1,285 @@
+use overl::file::File;
+use overl::merge::{ArrayMerge, MergeStrategy};
+use serde_json::json;
+
+fn base() -> File {
+    File::new(json!({
+        "metadata": {
+            "name": "guestbook",
+            "labels": { "name": "guestbook", "team": "platform" },
+            "finalizers": ["first", "second", "third"]
+        },
+        "spec": {
+            "source": { "chart": "guestbook", "path": "charts" }
+        }
+    }))
+}
+
+#[test]
+fn delete_sentinel_removes_nested_keys() {
+    let mut file = base();
+    file.merge(File::new(json!({
+        "metadata": { "labels": { "team": "~delete" } },
+        "spec": { "source": { "chart": "~delete" } }
+    })));
+    assert_eq!(
+        file.data(),
+        &json!({
+            "metadata": {
+                "name": "guestbook",
+                "labels": { "name": "guestbook" },
+                "finalizers": ["first", "second", "third"]
+            },
+            "spec": { "source": { "path": "charts" } }
+        })
+    );
+}
+
+#[test]
+fn null_deletes_only_when_enabled() {
+    let overlay = json!({ "spec": { "source": { "chart": null } } });
+
+    let mut file = base();
+    file.merge(File::new(overlay.clone()));
+    assert_eq!(file.data()["spec"]["source"]["chart"], json!(null));
+
+    let mut file = base();
+    let strategy = MergeStrategy::default().with_null_deletes(true);
+    file.merge_with(File::new(overlay), &strategy);
+    assert_eq!(file.data()["spec"]["source"], json!({ "path": "charts" }));
+}
+
+#[test]
+fn delete_sentinel_removes_array_elements_by_index() {
+    let mut file = base();
+    let strategy = MergeStrategy::new(ArrayMerge::ByIndex);
+    file.merge_with(
+        File::new(json!({ "metadata": { "finalizers": ["~delete", "kept", "~delete"] } })),
+        &strategy,
+    );
+    assert_eq!(file.data()["metadata"]["finalizers"], json!(["kept"]));
+}
+
+#[test]
+fn new_keys_do_not_keep_deletion_markers() {
+    let mut file = base();
+    let strategy = MergeStrategy::default().with_null_deletes(true);
+    file.merge_with(
+        File::new(json!({ "spec": { "destination": { "server": "~delete", "namespace": null } } })),
+        &strategy,
+    );
+    assert_eq!(file.data()["spec"]["destination"], json!({}));
+}
+
+#[test]
+fn insert_delete_sentinel_removes_path() {
+    let mut file = base();
+    file.insert(
+        vec![
+            "metadata".to_owned(),
+            "labels".to_owned(),
+            "team".to_owned(),
+        ],
+        json!("~delete"),
+    );
+    file.insert(
+        vec![
+            "metadata".to_owned(),
+            "finalizers".to_owned(),
+            "$1".to_owned(),
+        ],
+        json!("~delete"),
+    );
+    assert_eq!(
+        file.data()["metadata"],
+        json!({
+            "name": "guestbook",
+            "labels": { "name": "guestbook" },
+            "finalizers": ["first", "third"]
+        })
+    );
+}
+
+#[test]
+fn patch_delete_items_remove_matching_base_items() {
+    let mut file = File::new(json!({ "items": [{ "n": 1 }, { "n": 2 }, { "n": 3 }] }));
+    file.merge(File::new(
+        json!({ "items": [{ "n": 2, "$patch": "delete" }, { "$patch": "delete" }] }),
+    ));
+    assert_eq!(file.data()["items"], json!([{ "n": 1 }, { "n": 3 }]));
+}
+
+#[test]
+fn patch_delete_items_remove_by_index() {
+    let mut file = File::new(json!({ "items": [{ "n": 1 }, { "n": 2 }, { "n": 3 }] }));
+    let strategy = MergeStrategy::new(ArrayMerge::ByIndex);
+    file.merge_with(
+        File::new(json!({ "items": [{ "m": 1 }, { "$patch": "delete" }, { "m": 3 }] })),
+        &strategy,
+    );
+    assert_eq!(
+        file.data()["items"],
+        json!([{ "n": 1, "m": 1 }, { "n": 3, "m": 3 }])
+    );
+}
+
+#[test]
+fn patch_replace_replaces_objects_and_arrays() {
+    let mut file = base();
+    file.merge(File::new(json!({
+        "metadata": { "labels": { "$patch": "replace", "owner": "web" } },
+        "spec": { "source": { "$patch": "delete" } }
+    })));
+    file.merge(File::new(json!({
+        "metadata": { "finalizers": [{ "$patch": "replace" }, "only"] }
+    })));
+    assert_eq!(
+        file.data(),
+        &json!({
+            "metadata": {
+                "name": "guestbook",
+                "labels": { "owner": "web" },
+                "finalizers": ["only"]
+            },
+            "spec": {}
+        })
+    );
+}
+
+#[test]
+fn retain_keys_removes_unlisted_base_keys() {
+    let mut file = base();
+    file.merge(File::new(json!({
+        "metadata": {
+            "$retainKeys": ["name", "annotations"],
+            "annotations": { "owner": "web" }
+        }
+    })));
+    assert_eq!(
+        file.data()["metadata"],
+        json!({ "name": "guestbook", "annotations": { "owner": "web" } })
+    );
+}
+
+#[test]
+fn set_element_order_reorders_items() {
+    let mut file = base();
+    file.merge(File::new(json!({
+        "metadata": { "$setElementOrder/finalizers": ["third", "first"] }
+    })));
+    assert_eq!(
+        file.data()["metadata"]["finalizers"],
+        json!(["third", "first", "second"])
+    );
+}
+
+#[test]
+fn deletion_markers_never_end_up_in_arrays() {
+    for arrays in [
+        ArrayMerge::Append,
+        ArrayMerge::Prepend,
+        ArrayMerge::Replace,
+        ArrayMerge::ByKey("name".to_owned()),
+    ] {
+        let mut file = File::new(json!({ "a": [1, 2, 3] }));
+        let strategy = MergeStrategy::new(arrays.clone()).with_null_deletes(true);
+        file.merge_with(File::new(json!({ "a": ["~delete", null, 4] })), &strategy);
+        let expected = match arrays {
+            ArrayMerge::Prepend => json!([4, 1, 2, 3]),
+            ArrayMerge::Replace => json!([4]),
+            _ => json!([1, 2, 3, 4]),
+        };
+        assert_eq!(file.data()["a"], expected, "{}", arrays);
+    }
+}
+
+fn merge_finalizers(arrays: ArrayMerge, overlay: serde_json::Value) -> serde_json::Value {
+    let mut file = base();
+    file.merge_with(
+        File::new(json!({ "metadata": { "finalizers": overlay } })),
+        &MergeStrategy::new(arrays),
+    );
+    file.data()["metadata"]["finalizers"].clone()
+}
+
+#[test]
+fn append_adds_overlay_items_after_base_items() {
+    assert_eq!(
+        merge_finalizers(ArrayMerge::Append, json!(["fourth"])),
+        json!(["first", "second", "third", "fourth"])
+    );
+}
+
+#[test]
+fn replace_uses_overlay_items_only() {
+    assert_eq!(
+        merge_finalizers(ArrayMerge::Replace, json!(["only"])),
+        json!(["only"])
+    );
+}
+
+#[test]
+fn prepend_adds_overlay_items_before_base_items() {
+    assert_eq!(
+        merge_finalizers(ArrayMerge::Prepend, json!(["zeroth", "half"])),
+        json!(["zeroth", "half", "first", "second", "third"])
+    );
+}
+
+#[test]
+fn index_merges_items_at_the_same_position() {
+    let mut file = File::new(json!({ "ports": [{ "port": 80 }, { "port": 443 }] }));
+    file.merge_with(
+        File::new(json!({ "ports": [{ "name": "http" }, { "port": 8443 }, { "port": 9000 }] })),
+        &MergeStrategy::new(ArrayMerge::ByIndex),
+    );
+    assert_eq!(
+        file.data()["ports"],
+        json!([{ "port": 80, "name": "http" }, { "port": 8443 }, { "port": 9000 }])
+    );
+}
+
+#[test]
+fn key_merges_items_sharing_the_field() {
+    let mut file = File::new(json!({
+        "parameters": [{ "name": "a", "value": "1" }, { "name": "b", "value": "2" }]
+    }));
+    file.merge_with(
+        File::new(json!({
+            "parameters": [{ "name": "b", "value": "20" }, { "name": "c", "value": "3" }]
+        })),
+        &MergeStrategy::new(ArrayMerge::ByKey("name".to_owned())),
+    );
+    assert_eq!(
+        file.data()["parameters"],
+        json!([
+            { "name": "a", "value": "1" },
+            { "name": "b", "value": "20" },
+            { "name": "c", "value": "3" }
+        ])
+    );
+}
+
+#[test]
+fn path_overrides_apply_to_their_array_only() {
+    let mut file = File::new(json!({
+        "spec": { "parameters": [{ "name": "a", "value": "1" }] },
+        "finalizers": ["first"]
+    }));
+    let strategy = MergeStrategy::new(ArrayMerge::Replace)
+        .with_path("/spec/parameters", ArrayMerge::ByKey("name".to_owned()));
+    file.merge_with(
+        File::new(json!({
+            "spec": { "parameters": [{ "name": "a", "value": "10" }] },
+            "finalizers": ["second"]
+        })),
+        &strategy,
+    );
+    assert_eq!(
+        file.data(),
+        &json!({
+            "spec": { "parameters": [{ "name": "a", "value": "10" }] },
+            "finalizers": ["second"]
+        })
+    );
+}
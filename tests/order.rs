@@ -0,0 +1,103 @@
+mod common;
+
+use common::Sandbox;
+use overl::file::File;
+use serde_json::json;
+
+fn keys(value: &serde_json::Value) -> Vec<&str> {
+    value
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn from_yaml_keeps_source_order() {
+    let sandbox = Sandbox::new();
+    let path = sandbox.write(
+        "source.yaml",
+        "kind: Application\napiVersion: v1\nmetadata:\n  name: a\n",
+    );
+    let file = File::from_yaml(&path).unwrap();
+    assert_eq!(keys(file.data()), ["kind", "apiVersion", "metadata"]);
+}
+
+#[test]
+fn merge_keeps_base_order_and_appends_new_keys() {
+    let mut base = File::new(json!({ "zeta": 1, "alpha": { "y": 1, "b": 2 }, "mid": 3 }));
+    base.merge(File::new(
+        json!({ "new": 4, "alpha": { "a": 5, "y": 6 }, "zeta": 7 }),
+    ));
+    assert_eq!(keys(base.data()), ["zeta", "alpha", "mid", "new"]);
+    assert_eq!(keys(&base.data()["alpha"]), ["y", "b", "a"]);
+}
+
+#[test]
+fn deleting_keys_keeps_order_of_the_others() {
+    let mut base = File::new(json!({ "a": 1, "b": 2, "c": 3, "d": 4 }));
+    base.merge(File::new(json!({ "b": "~delete" })));
+    assert_eq!(keys(base.data()), ["a", "c", "d"]);
+}
+
+#[test]
+fn insert_keeps_order() {
+    let mut file = File::new(json!({ "spec": 1, "metadata": { "name": "a" } }));
+    file.insert(
+        vec!["spec".to_owned(), "project".to_owned()],
+        json!("default"),
+    );
+    file.insert(
+        vec!["metadata".to_owned(), "labels".to_owned()],
+        json!({ "b": 1 }),
+    );
+    file.insert(vec!["apiVersion".to_owned()], json!("v1"));
+    assert_eq!(keys(file.data()), ["spec", "metadata", "apiVersion"]);
+    assert_eq!(keys(&file.data()["metadata"]), ["name", "labels"]);
+}
+
+#[test]
+fn yaml_round_trip_keeps_order() {
+    let sandbox = Sandbox::new();
+    let source = "---\nkind: Application\napiVersion: v1\nspec:\n  z: 1\n  a: 2";
+    let path = sandbox.write("round-trip.yaml", source);
+    let file = File::from_yaml(&path).unwrap();
+    assert_eq!(file.to_yaml_string(), source);
+}
+
+#[test]
+fn canonicalize_orders_keys_by_schema() {
+    let schema = json!({
+        "$ref": "#/definitions/Application",
+        "definitions": {
+            "Application": {
+                "type": "object",
+                "properties": {
+                    "apiVersion": { "type": "string" },
+                    "kind": { "type": "string" },
+                    "metadata": { "$ref": "#/definitions/Metadata" }
+                }
+            },
+            "Metadata": {
+                "type": "object",
+                "properties": { "name": {}, "namespace": {} }
+            }
+        }
+    });
+    let mut file = File::new(json!({
+        "spec": {},
+        "metadata": { "labels": {}, "namespace": "argocd", "name": "a" },
+        "kind": "Application",
+        "apiVersion": "v1"
+    }));
+    file.canonicalize(&schema);
+    assert_eq!(
+        keys(file.data()),
+        ["apiVersion", "kind", "metadata", "spec"]
+    );
+    assert_eq!(
+        keys(&file.data()["metadata"]),
+        ["name", "namespace", "labels"]
+    );
+}
@@ -0,0 +1,59 @@
+use overl::error::Error;
+use overl::file::File;
+use overl::merge::MergeStrategy;
+use overl::render;
+use serde_json::json;
+use std::path::Path;
+
+fn documents(values: &[serde_json::Value]) -> Vec<File> {
+    values.iter().cloned().map(File::new).collect()
+}
+
+#[test]
+fn single_base_documents_only_take_overlays_of_their_kind() {
+    let strategy = MergeStrategy::default();
+    let mut base = documents(&[json!({"kind": "Deployment", "replicas": 1})]);
+    let overlay = documents(&[
+        json!({"replicas": 2}),
+        json!({"kind": "Deployment", "metadata": {"name": "web"}}),
+    ]);
+    render::merge_documents(&mut base, overlay, &strategy, Path::new("prod.yaml")).unwrap();
+    assert_eq!(
+        base[0].data(),
+        &json!({"kind": "Deployment", "replicas": 2, "metadata": {"name": "web"}})
+    );
+
+    let overlay = documents(&[json!({"kind": "Service"})]);
+    let error = render::merge_documents(&mut base, overlay, &strategy, Path::new("prod.yaml"));
+    assert!(matches!(
+        error,
+        Err(Error::UnmatchedDocument { index: 0, .. })
+    ));
+    assert_eq!(base[0].data()["kind"], json!("Deployment"));
+}
+
+#[test]
+fn multi_document_bases_fail_on_unmatched_overlay_documents() {
+    let strategy = MergeStrategy::default();
+    let mut base = documents(&[
+        json!({"kind": "Service", "metadata": {"name": "web"}}),
+        json!({"kind": "Deployment", "metadata": {"name": "web"}}),
+    ]);
+    let overlay = documents(&[
+        json!({"metadata": {"name": "web", "labels": {"app": "web"}}}),
+        json!({"kind": "Deployment", "replicas": 2}),
+    ]);
+    render::merge_documents(&mut base, overlay, &strategy, Path::new("prod.yaml")).unwrap();
+    assert_eq!(base[0].data()["metadata"]["labels"], json!({"app": "web"}));
+    assert_eq!(base[1].data()["metadata"]["labels"], json!({"app": "web"}));
+    assert_eq!(base[1].data()["replicas"], json!(2));
+
+    for unmatched in [json!({"replicas": 3}), json!({"kind": "Ingress"})] {
+        let overlay = documents(&[json!({"kind": "Service"}), unmatched]);
+        let error = render::merge_documents(&mut base, overlay, &strategy, Path::new("prod.yaml"));
+        assert!(
+            matches!(&error, Err(Error::UnmatchedDocument { path, index: 1 }) if path == Path::new("prod.yaml"))
+        );
+    }
+    assert_eq!(base.len(), 2);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::Sandbox;
+use overl::file::File;
+use overl::report::ValidationReport;
+use serde_json::json;
+
+#[test]
+fn report_lists_every_error_with_its_source() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write(
+        "base.yaml",
+        "metadata:\n  name: app\nspec:\n  replicas: 1\n",
+    );
+    let overlay = sandbox.write("overlay.yaml", "spec:\n  replicas: two\n");
+    let mut file = File::load_documents(&base).unwrap().remove(0);
+    file.merge(File::load_documents(&overlay).unwrap().remove(0));
+    let schema = json!({
+        "type": "object",
+        "required": ["kind"],
+        "properties": {
+            "spec": {
+                "type": "object",
+                "properties": {"replicas": {"type": "integer"}}
+            }
+        }
+    });
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    let report = ValidationReport::new(&validator, std::slice::from_ref(&file));
+
+    assert_eq!(report.errors.len(), 2);
+    let required = &report.errors[0];
+    assert_eq!(required.keyword, "required");
+    assert_eq!(required.source.as_ref().unwrap().path, base);
+    let replicas = &report.errors[1];
+    assert_eq!(replicas.pointer, "/spec/replicas");
+    assert_eq!(replicas.keyword, "type");
+    let source = replicas.source.as_ref().unwrap();
+    assert_eq!((&source.path, source.line), (&overlay, 2));
+
+    assert_eq!(file.validate_with(&validator).errors.len(), 2);
+    assert!(file.validate(&schema).is_err());
+}
+
+#[test]
+fn unknown_properties_are_located_where_they_were_added() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write("base.yaml", "name: app\nspec:\n  replicas: 1\n");
+    let overlay = sandbox.write("testrequest.yaml", "spec:\n  replicas: 2\nmytest: cheese\n");
+    let mut file = File::load_documents(&base).unwrap().remove(0);
+    file.merge(File::load_documents(&overlay).unwrap().remove(0));
+    let schema = json!({
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {"name": {"type": "string"}, "spec": {"type": "object"}}
+    });
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    let report = ValidationReport::new(&validator, std::slice::from_ref(&file));
+
+    assert_eq!(report.errors.len(), 1);
+    let error = &report.errors[0];
+    assert_eq!(error.keyword, "additionalProperties");
+    assert_eq!(error.pointer, "/mytest");
+    let source = error.source.as_ref().unwrap();
+    assert_eq!((&source.path, source.line), (&overlay, 3));
+}
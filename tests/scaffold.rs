@@ -0,0 +1,172 @@
+mod common;
+
+use common::Sandbox;
+use overl::config::{Config, Project};
+use overl::error::Error;
+use overl::file::{File, Format, LoadOptions};
+use overl::merge::MergeStrategy;
+use overl::{extract, init, render, scaffold, schema};
+use serde_json::json;
+use std::fs;
+
+#[test]
+fn skeleton_holds_required_properties_missing_from_base() {
+    let schema = json!({
+        "type": "object",
+        "required": ["metadata", "spec"],
+        "properties": {
+            "metadata": {
+                "type": "object",
+                "required": ["name", "namespace"],
+                "properties": {
+                    "name": {"type": "string", "description": "Name of the app"},
+                    "namespace": {"type": "string", "default": "default"}
+                }
+            },
+            "spec": {"$ref": "#/definitions/Spec"}
+        },
+        "definitions": {
+            "Spec": {
+                "type": "object",
+                "required": ["policy", "replicas"],
+                "properties": {
+                    "policy": {"type": "string", "enum": ["Always", "Never"]},
+                    "replicas": {"type": "integer"}
+                }
+            }
+        }
+    });
+    let base = json!({"metadata": {"namespace": "apps"}});
+    let skeleton = scaffold::overlay_skeleton(&base, &schema);
+    assert_eq!(
+        skeleton.data(),
+        &json!({
+            "metadata": {"name": ""},
+            "spec": {"policy": "Always", "replicas": 0}
+        })
+    );
+    assert_eq!(
+        skeleton.to_yaml_string(),
+        "---\nmetadata:\n  # string - Name of the app\n  name: \"\"\n# object\nspec:\n  # string, one of: Always, Never\n  policy: Always\n  # integer\n  replicas: 0"
+    );
+}
+
+#[test]
+fn import_converts_files_to_the_project_format() {
+    let sandbox = Sandbox::new();
+    let root = sandbox.path();
+    let sources = root.join("sources");
+    fs::create_dir_all(&sources).unwrap();
+    fs::write(
+        sources.join("deployment.json"),
+        r#"{"kind": "Deployment", "replicas": 1}"#,
+    )
+    .unwrap();
+    fs::write(sources.join("prod.yaml"), "# production\nreplicas: 3\n").unwrap();
+    let project = Project::new(root, Config::default());
+
+    scaffold::import_template(
+        &project,
+        "app",
+        &sources.join("deployment.json"),
+        None,
+        &[sources.join("prod.yaml")],
+    )
+    .unwrap();
+    let template = project.template_dir("app");
+    assert_eq!(
+        File::load(template.join("base.yaml")).unwrap().data(),
+        &json!({"kind": "Deployment", "replicas": 1})
+    );
+    assert_eq!(
+        fs::read_to_string(project.overlays_dir("app").join("prod.yaml")).unwrap(),
+        "# production\nreplicas: 3\n"
+    );
+    let inferred = File::load(template.join("schema.yaml")).unwrap();
+    assert!(schema::check(&inferred).is_valid());
+    let rendered = render::render_template(
+        &project,
+        "app",
+        &["prod".to_owned()],
+        &MergeStrategy::default(),
+        &LoadOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        rendered[0].data(),
+        &json!({"kind": "Deployment", "replicas": 3})
+    );
+    assert!(
+        scaffold::import_template(&project, "app", &sources.join("prod.yaml"), None, &[]).is_err()
+    );
+
+    let json_project = Project::new(
+        root,
+        Config {
+            format: Format::Json,
+            ..Config::default()
+        },
+    );
+    scaffold::import_template(
+        &json_project,
+        "values",
+        &sources.join("prod.yaml"),
+        None,
+        &[],
+    )
+    .unwrap();
+    assert!(json_project
+        .template_dir("values")
+        .join("base.json")
+        .is_file());
+}
+
+#[test]
+fn names_that_leave_their_folder_are_rejected() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write("base.yaml", "kind: Deployment\n");
+    let project = Project::new(sandbox.path(), Config::default());
+    let deployment = init::catalogue_template("deployment").unwrap();
+    let sources = [base];
+    for name in ["../x", "a/b", "a\\b", "..", ""] {
+        let results = [
+            scaffold::new_overlay(&project, "app", name),
+            scaffold::import_template(&project, name, &sources[0], None, &[]),
+            init::add_template(&project, deployment, name),
+            extract::extract_template(&project, name, &sources),
+        ];
+        for result in results {
+            assert!(
+                matches!(&result, Err(Error::InvalidName { name: invalid }) if invalid == name),
+                "{}: {:?}",
+                name,
+                result
+            );
+        }
+    }
+    assert!(!sandbox.path().join("x").exists());
+    assert!(!project.templates_dir().exists());
+}
+
+#[test]
+fn import_checks_renders_against_the_given_schema() {
+    let sandbox = Sandbox::new();
+    let base = sandbox.write("sources/deployment.yaml", "kind: Deployment\nreplicas: 1\n");
+    let overlays = [sandbox.write("sources/prod.yaml", "replicas: 3\n")];
+    let schema = sandbox.write(
+        "sources/schema.yaml",
+        "type: object\nproperties:\n  replicas:\n    maximum: 2\n",
+    );
+    let project = Project::new(sandbox.path(), Config::default());
+
+    let result = scaffold::import_template(&project, "app", &base, Some(&schema), &overlays);
+    assert!(matches!(result, Err(Error::Invalid { path, .. }) if path == overlays[0]));
+    assert!(!project.template_dir("app").exists());
+
+    let strict = sandbox.write("sources/strict.yaml", "type: object\nrequired: [image]\n");
+    let result = scaffold::import_template(&project, "app", &base, Some(&strict), &[]);
+    assert!(matches!(result, Err(Error::Invalid { path, .. }) if path == base));
+
+    scaffold::import_template(&project, "app", &base, Some(&schema), &[]).unwrap();
+    assert!(project.template_dir("app").join("schema.yaml").is_file());
+}
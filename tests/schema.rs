@@ -0,0 +1,117 @@
+use overl::file::File;
+use overl::schema;
+use serde_json::json;
+use std::path::Path;
+
+#[test]
+fn check_reports_broken_schemas() {
+    let schema = File::new(json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "properties": {
+            "replica": {"type": "objec", "colour": "red"},
+            "metadata": {"$ref": "#/definitions/Metadata"}
+        }
+    }));
+    let report = schema::check(&schema);
+    let errors: Vec<(&str, &str)> = report
+        .errors
+        .iter()
+        .map(|error| (error.pointer.as_str(), error.keyword.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("/properties/replica/type", "anyOf"),
+            ("/properties/metadata/$ref", "$ref"),
+        ]
+    );
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].pointer, "/properties/replica/colour");
+    assert_eq!(report.warnings[0].keyword, "unknown");
+}
+
+#[test]
+fn validator_accepts_unknown_keywords() {
+    let schema = File::new(json!({
+        "type": "object",
+        "properties": {
+            "repoURL": {"type": "string", "qt-uri-protocols": ["https"]}
+        }
+    }));
+    let report = schema::check(&schema);
+    assert!(report.is_valid());
+    assert_eq!(report.warnings.len(), 1);
+    assert!(schema::validator(&schema, Path::new("schema.yaml")).is_ok());
+}
+
+#[test]
+fn check_accepts_valid_schemas() {
+    let schema = File::new(json!({
+        "$schema": "http://json-schema.org/draft-06/schema#",
+        "type": "object",
+        "x-order": 1,
+        "properties": {"metadata": {"$ref": "#/definitions/Metadata"}},
+        "definitions": {"Metadata": {"type": "object", "required": ["name"]}}
+    }));
+    assert!(schema::check(&schema).is_valid());
+}
+
+#[test]
+fn apply_defaults_fills_missing_properties() {
+    let schema = json!({
+        "type": "object",
+        "required": ["metadata", "spec"],
+        "properties": {
+            "metadata": {"$ref": "#/definitions/Metadata"},
+            "spec": {
+                "type": "object",
+                "properties": {"replicas": {"type": "integer", "default": 1}}
+            },
+            "status": {
+                "type": "object",
+                "properties": {"phase": {"type": "string", "default": "new"}}
+            }
+        },
+        "definitions": {
+            "Metadata": {
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "namespace": {"type": "string", "default": "default"}
+                }
+            }
+        }
+    });
+    let mut file = File::new(json!({"metadata": {"name": "app"}}));
+    file.apply_defaults(&schema);
+    assert_eq!(
+        file.data(),
+        &json!({
+            "metadata": {"name": "app", "namespace": "default"},
+            "spec": {"replicas": 1}
+        })
+    );
+}
+
+#[test]
+fn infer_drafts_schema_from_samples() {
+    let prod = json!({"kind": "Deployment", "env": "prod", "replicas": 3, "ports": [80]});
+    let dev = json!({"kind": "Deployment", "env": "dev", "ports": [8080, 8.5]});
+    let inferred = schema::infer(&[&prod, &dev]);
+    assert_eq!(
+        inferred,
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "kind": {"type": "string"},
+                "env": {"type": "string", "enum": ["prod", "dev"]},
+                "replicas": {"type": "integer"},
+                "ports": {"type": "array", "items": {"type": "number"}}
+            },
+            "required": ["kind", "env", "ports"]
+        })
+    );
+    assert!(schema::check(&File::new(inferred)).is_valid());
+}
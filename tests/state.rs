@@ -0,0 +1,237 @@
+mod common;
+
+use common::Sandbox;
+use overl::config::Project;
+use overl::merge::MergeStrategy;
+use overl::state;
+use serde_json::json;
+use std::fs;
+
+/// Creates a project with a single template holding one overlay
+fn project(sandbox: &Sandbox) -> Project {
+    sandbox.write("templates/app/base.yaml", "name: app\n");
+    sandbox.write("templates/app/schema.yaml", "type: object\n");
+    sandbox.write("templates/app/overlays/prod.yaml", "env: prod\n");
+    let config = "name = \"test\"\ntemplates = \"templates\"\nstate = \"state.json\"\n";
+    sandbox.write("overl.toml", config);
+    Project::discover(sandbox.path()).unwrap()
+}
+
+#[test]
+fn implementations_are_added_listed_and_removed() {
+    let sandbox = Sandbox::new();
+    let project = project(&sandbox);
+    state::sync_state_file(&project).unwrap();
+    state::add_implementation(
+        &project,
+        "app",
+        "app-prod",
+        None,
+        &["prod".to_owned()],
+        json!({"replicas": 2}),
+    )
+    .unwrap();
+    assert!(
+        state::add_implementation(&project, "app", "app-prod", None, &[], json!(null)).is_err()
+    );
+    assert!(
+        state::add_implementation(&project, "app", "other", Some(7), &[], json!(null)).is_err()
+    );
+    assert!(state::add_implementation(
+        &project,
+        "app",
+        "other",
+        None,
+        &["missing".to_owned()],
+        json!(null)
+    )
+    .is_err());
+
+    let implementations = state::list_implementations(&project).unwrap();
+    assert_eq!(implementations.len(), 1);
+    let (template, implementation) = &implementations[0];
+    assert_eq!(template, "app");
+    assert_eq!(implementation.version, 0);
+    assert_eq!(implementation.overlays, ["prod.yaml"]);
+    assert_eq!(implementation.overlay, json!({"replicas": 2}));
+
+    // syncing again keeps implementations
+    state::sync_state_file(&project).unwrap();
+    assert_eq!(state::list_implementations(&project).unwrap().len(), 1);
+
+    state::remove_implementation(&project, "app-prod").unwrap();
+    assert!(state::list_implementations(&project).unwrap().is_empty());
+    assert!(state::remove_implementation(&project, "app-prod").is_err());
+}
+
+#[test]
+fn implementations_render_their_pinned_version() {
+    let sandbox = Sandbox::new();
+    let project = project(&sandbox);
+    state::sync_state_file(&project).unwrap();
+    state::add_implementation(
+        &project,
+        "app",
+        "app-prod",
+        None,
+        &["prod".to_owned()],
+        json!({"replicas": 2}),
+    )
+    .unwrap();
+    fs::write(
+        project.template_dir("app").join("base.yaml"),
+        "name: app\nimage: nginx\n",
+    )
+    .unwrap();
+    state::sync_state_file(&project).unwrap();
+
+    let strategy = MergeStrategy::default();
+    let pinned = state::render_implementation(&project, "app-prod", None, &strategy).unwrap();
+    assert_eq!(pinned.version, 0);
+    assert_eq!(
+        pinned.documents[0].data(),
+        &json!({"name": "app", "env": "prod", "replicas": 2})
+    );
+
+    state::upgrade_implementation(&project, "app-prod", 1).unwrap();
+    let upgraded = state::render_implementation(&project, "app-prod", None, &strategy).unwrap();
+    assert_eq!(upgraded.version, 1);
+    assert_eq!(
+        upgraded.documents[0].data(),
+        &json!({"name": "app", "image": "nginx", "env": "prod", "replicas": 2})
+    );
+}
+
+#[test]
+fn implementations_render_multi_document_and_array_files() {
+    let sandbox = Sandbox::new();
+    let project = project(&sandbox);
+    sandbox.write("templates/list/base.yaml", "- a\n- b\n");
+    sandbox.write("templates/list/schema.yaml", "type: array\n");
+    sandbox.write("templates/list/overlays/more.yaml", "- c\n");
+    sandbox.write(
+        "templates/app/base.yaml",
+        "kind: Service\nname: app\n---\nkind: Deployment\nname: app\n",
+    );
+    sandbox.write(
+        "templates/app/overlays/prod.yaml",
+        "kind: Deployment\nenv: prod\n",
+    );
+    state::sync_state_file(&project).unwrap();
+    state::add_implementation(
+        &project,
+        "list",
+        "list",
+        None,
+        &["more".to_owned()],
+        json!(null),
+    )
+    .unwrap();
+    state::add_implementation(
+        &project,
+        "app",
+        "app",
+        None,
+        &["prod".to_owned()],
+        json!(null),
+    )
+    .unwrap();
+
+    let strategy = MergeStrategy::default();
+    let list = state::render_implementation(&project, "list", None, &strategy).unwrap();
+    let documents: Vec<_> = list
+        .documents
+        .iter()
+        .map(|document| document.data())
+        .collect();
+    assert_eq!(documents, [&json!(["a", "b", "c"])]);
+    let app = state::render_implementation(&project, "app", None, &strategy).unwrap();
+    let documents: Vec<_> = app
+        .documents
+        .iter()
+        .map(|document| document.data())
+        .collect();
+    assert_eq!(
+        documents,
+        [
+            &json!({"kind": "Service", "name": "app"}),
+            &json!({"kind": "Deployment", "name": "app", "env": "prod"})
+        ]
+    );
+}
+
+#[test]
+fn sync_adds_a_version_when_content_changes() {
+    let sandbox = Sandbox::new();
+    let project = project(&sandbox);
+    state::sync_state_file(&project).unwrap();
+    state::add_implementation(&project, "app", "app", None, &[], json!(null)).unwrap();
+    assert_eq!(state::current_version(&project, "app").unwrap(), 0);
+
+    // formatting and comments don't change the content
+    sandbox.write("templates/app/base.yaml", "# the app\nname:   app\n");
+    state::sync_state_file(&project).unwrap();
+    assert_eq!(state::current_version(&project, "app").unwrap(), 0);
+
+    let edits = [
+        ("templates/app/base.yaml", "name: web\n"),
+        ("templates/app/overlays/prod.yaml", "env: production\n"),
+        ("templates/app/overlays/dev.yaml", "env: dev\n"),
+        (
+            "templates/app/schema.yaml",
+            "type: object\nrequired: [name]\n",
+        ),
+    ];
+    for (version, (path, content)) in (1..).zip(edits) {
+        sandbox.write(path, content);
+        state::sync_state_file(&project).unwrap();
+        assert_eq!(
+            state::current_version(&project, "app").unwrap(),
+            version,
+            "{}",
+            path
+        );
+        state::sync_state_file(&project).unwrap();
+        assert_eq!(
+            state::current_version(&project, "app").unwrap(),
+            version,
+            "{}",
+            path
+        );
+    }
+    let strategy = MergeStrategy::default();
+    let rendered = state::render_implementation(&project, "app", Some(0), &strategy).unwrap();
+    assert_eq!(rendered.documents[0].data(), &json!({"name": "app"}));
+}
+
+#[test]
+fn sync_skips_stray_files_and_missing_overlays_folders() {
+    let sandbox = Sandbox::new();
+    let project = project(&sandbox);
+    // files next to the template folders aren't templates
+    sandbox.write("templates/README", "templates of the project\n");
+    sandbox.write("templates/bare/base.yaml", "name: bare\n");
+    sandbox.write("templates/bare/schema.yaml", "type: object\n");
+    state::sync_state_file(&project).unwrap();
+    state::add_implementation(&project, "bare", "bare", None, &[], json!(null)).unwrap();
+    let strategy = MergeStrategy::default();
+    let rendered = state::render_implementation(&project, "bare", None, &strategy).unwrap();
+    assert_eq!(rendered.documents[0].data(), &json!({"name": "bare"}));
+}
+
+#[test]
+fn sync_returns_unknown_schema_keywords() {
+    let sandbox = Sandbox::new();
+    let project = project(&sandbox);
+    assert!(state::sync_state_file(&project).unwrap().is_empty());
+    sandbox.write(
+        "templates/app/schema.yaml",
+        "type: object\nrequird: [name]\n",
+    );
+    let warnings = state::sync_state_file(&project).unwrap();
+    assert_eq!(warnings.len(), 1);
+    let (template, warning) = &warnings[0];
+    assert_eq!(template, "app");
+    assert_eq!(warning.pointer, "/requird");
+    assert_eq!(warning.source.as_ref().unwrap().line, 2);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::Sandbox;
+use overl::file::{Error, File, LoadOptions};
+use serde_json::json;
+
+const SOURCE: &str = "title = \"release\"\nreleased = 2024-05-27T07:32:00Z\n\n[owner]\nname = \"web\"\nborn = 1979-05-27\n";
+
+#[test]
+fn datetimes_are_read_as_strings() {
+    let sandbox = Sandbox::new();
+    let path = sandbox.write("release.toml", SOURCE);
+    let file = File::load(&path).unwrap();
+    assert_eq!(
+        file.data(),
+        &json!({
+            "title": "release",
+            "released": "2024-05-27T07:32:00Z",
+            "owner": {"name": "web", "born": "1979-05-27"}
+        })
+    );
+    // written back, they are strings
+    assert!(file
+        .to_toml_string()
+        .unwrap()
+        .contains("released = \"2024-05-27T07:32:00Z\""));
+}
+
+#[test]
+fn lossless_datetimes_round_trip() {
+    let sandbox = Sandbox::new();
+    let path = sandbox.write("release.toml", SOURCE);
+    let options = LoadOptions {
+        comments: false,
+        lossless: true,
+    };
+    let file = File::load_documents_with(&path, &options)
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        file.data()["released"],
+        json!({"$tag": "!!timestamp", "$value": "2024-05-27T07:32:00Z"})
+    );
+    assert_eq!(file.to_toml_string().unwrap(), SOURCE);
+    assert!(file
+        .to_yaml_string()
+        .contains("released: !!timestamp \"2024-05-27T07:32:00Z\""));
+}
+
+#[test]
+fn toml_errors_are_reported() {
+    let sandbox = Sandbox::new();
+    let path = sandbox.write("broken.toml", "name = \"web\"\nport = \n");
+    assert!(matches!(
+        File::load(&path),
+        Err(Error::Toml { line: 2, .. })
+    ));
+    let output = sandbox.path().join("null.toml");
+    let error = File::new(json!({"spec": {"names": ["a", null]}}))
+        .save(&output)
+        .unwrap_err();
+    assert!(
+        matches!(&error, Error::Unrepresentable { path: Some(path), format: "toml", .. } if *path == output)
+    );
+    assert!(error.to_string().ends_with("found at /spec/names/1"));
+    assert!(File::new(json!([1, 2])).to_toml_string().is_err());
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::Sandbox;
+use overl::config::{Config, Project};
+use overl::file::LoadOptions;
+use overl::merge::MergeStrategy;
+use overl::{render, validate};
+
+#[test]
+fn check_templates_renders_overlays_of_the_template() {
+    let sandbox = Sandbox::new();
+    sandbox.write("app/base.yaml", "name: app\nreplicas: 1\n");
+    sandbox.write(
+        "app/schema.yaml",
+        "type: object\nproperties:\n  replicas:\n    type: integer\n",
+    );
+    sandbox.write("app/overlays/prod.yaml", "replicas: 3\n");
+    let bad = sandbox.write("app/overlays/test.yaml", "replicas: many\n");
+    let project = Project::new(
+        sandbox.path(),
+        Config {
+            templates: ".".into(),
+            ..Config::default()
+        },
+    );
+
+    let checks = validate::check_templates(
+        &project,
+        &MergeStrategy::default(),
+        &LoadOptions::default(),
+        false,
+    )
+    .unwrap();
+    let outcomes: Vec<(Option<&str>, bool)> = checks
+        .iter()
+        .map(|check| (check.overlay.as_deref(), check.is_valid()))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [(Some("prod.yaml"), true), (Some("test.yaml"), false)]
+    );
+    let report = checks[1].outcome.as_ref().unwrap();
+    assert_eq!(report.errors[0].source.as_ref().unwrap().path, bad);
+
+    // only arguments with a path separator are read from outside the overlays folder
+    let stray = sandbox.write("test.yaml", "replicas: 5\n");
+    let strategy = MergeStrategy::default();
+    let options = LoadOptions::default();
+    let by_name =
+        render::render_template(&project, "app", &["test".to_owned()], &strategy, &options)
+            .unwrap();
+    assert_eq!(by_name[0].data()["replicas"], "many");
+    let by_path = stray.to_string_lossy().into_owned();
+    let by_path =
+        render::render_template(&project, "app", &[by_path], &strategy, &options).unwrap();
+    assert_eq!(by_path[0].data()["replicas"], 5);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::Sandbox;
+use overl::file::{File, LoadOptions};
+use serde_json::json;
+
+const LOSSLESS: LoadOptions = LoadOptions {
+    comments: false,
+    lossless: true,
+};
+
+#[test]
+fn lossless_expands_anchors_and_stringifies_keys() {
+    let sandbox = Sandbox::new();
+    let path = sandbox.write(
+        "anchors.yaml",
+        "defaults: &defaults\n  retries: 3\nservice:\n  <<: *defaults\n  ports:\n    80: http\n    true: on\n  limit: .inf\n",
+    );
+    let file = File::load_documents_with(&path, &LOSSLESS)
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        file.data()["service"],
+        json!({
+            "ports": {"80": "http", "true": "on"},
+            "limit": {"$tag": "!!float", "$value": ".inf"},
+            "retries": 3
+        })
+    );
+    assert!(File::load_documents(&path).is_err());
+}
+
+#[test]
+fn lossless_tags_round_trip() {
+    let sandbox = Sandbox::new();
+    let source = "bucket: !Ref MyBucket\ndata: !!binary aGVsbG8=\ncondition: !If\n  - IsProd\n  - a\n  - b\nname: !Sub\n  prefix: app\nlimit: -.inf\n";
+    let path = sandbox.write("tags.yaml", source);
+    let file = File::load_documents_with(&path, &LOSSLESS)
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        file.data()["bucket"],
+        json!({"$tag": "!Ref", "$value": "MyBucket"})
+    );
+    assert_eq!(
+        file.data()["condition"],
+        json!({"$tag": "!If", "$value": ["IsProd", "a", "b"]})
+    );
+    assert_eq!(file.to_yaml_string(), format!("---\n{}", source.trim_end()));
+}
+
+#[test]
+fn lossless_rejects_collection_keys() {
+    let sandbox = Sandbox::new();
+    let path = sandbox.write("keys.yaml", "? [a, b]\n: 1\n");
+    assert!(File::load_documents_with(&path, &LOSSLESS).is_err());
+}
+
+#[test]
+fn lossless_tags_of_roots_and_items_round_trip() {
+    let sandbox = Sandbox::new();
+    for source in [
+        "--- !Config\nname: web\nports:\n  - !Port\n    number: 80\n  - !Port https\n  - !Empty []",
+        "--- !List\n- !Sub\n  - a\n  - b\n- !Ref c",
+        "---\n!Ref scalar",
+    ] {
+        let path = sandbox.write("root.yaml", source);
+        let file = File::load_documents_with(&path, &LOSSLESS)
+            .unwrap()
+            .remove(0);
+        assert_eq!(file.to_yaml_string(), source);
+    }
+}
+
+#[test]
+fn keys_that_read_as_other_types_stay_strings() {
+    let sandbox = Sandbox::new();
+    let source =
+        "\"200\": ok\n\"1.5\": half\n\"true\": yes\n\"\": empty\n\"~\": tilde\n\"null\": none\n";
+    let path = sandbox.write("keys.yaml", source);
+    for options in [LoadOptions::default(), LOSSLESS] {
+        let file = File::load_documents_with(&path, &options)
+            .unwrap()
+            .remove(0);
+        let emitted = sandbox.write("emitted.yaml", &file.to_yaml_string());
+        let reread = File::load_documents_with(&emitted, &options)
+            .unwrap()
+            .remove(0);
+        assert_eq!(reread.data(), file.data());
+        assert_eq!(
+            file.data(),
+            &json!({"200": "ok", "1.5": "half", "true": "yes", "": "empty", "~": "tilde", "null": "none"})
+        );
+    }
+}